@@ -0,0 +1,175 @@
+use std::{io::{BufRead, BufReader, Read, Write}, sync::atomic::{AtomicU64, Ordering}};
+
+use actix::prelude::*;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use futures::StreamExt;
+use tokio::sync::broadcast;
+
+use crate::{events, instance::Instance, messages::{instance_messages, native_messages}, native};
+
+/// lines kept for viewers that fall behind
+pub const BACKLOG: usize = 1024;
+
+pub type ConsoleStream = std::pin::Pin<Box<dyn futures::Stream<Item = String> + Send + 'static>>;
+
+/// output of the current process, one line per message
+pub type Console = broadcast::Sender<String>;
+
+pub fn console() -> Console {
+    broadcast::channel(BACKLOG).0
+}
+
+/// copies lines of process output to viewers and to our own output, as before it was piped
+pub fn pipe<R: Read + Send + 'static>(from: R, console: Console, stderr: bool, events: Option<events::Sink>) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(from);
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            if stderr {
+                let _ = std::io::stderr().write_all(&buf);
+            } else {
+                let _ = std::io::stdout().write_all(&buf);
+            }
+
+            let line = String::from_utf8_lossy(&buf).trim_end_matches(['\r', '\n']).to_owned();
+
+            if let Some(events) = &events {
+                events.line(&line);
+            }
+
+            // nobody watching is fine
+            let _ = console.send(line);
+        }
+    });
+}
+
+pub fn stream(console: &Console) -> ConsoleStream {
+    tokio_stream::wrappers::BroadcastStream::new(console.subscribe())
+        .filter_map(|line| async { line.ok() })
+        .boxed()
+}
+
+/// checked by handlers outside of graphql
+pub struct AdminPassword(pub String);
+
+#[derive(serde::Deserialize)]
+pub struct ConsoleParams {
+    name: String,
+}
+
+static SESSIONS: AtomicU64 = AtomicU64::new(0);
+
+/// first text frame is the password, every one after it is a line for stdin
+pub struct ConsoleSession {
+    id: u64,
+    instance: Addr<Instance>,
+    /// peer address, kept in the history of what it writes
+    caller: Option<String>,
+    password: String,
+    authorized: bool,
+}
+
+impl Actor for ConsoleSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.instance.do_send(instance_messages::ReleaseConsole { session: self.id });
+    }
+}
+
+impl StreamHandler<String> for ConsoleSession {
+    fn handle(&mut self, line: String, ctx: &mut Self::Context) {
+        ctx.text(line);
+    }
+
+    // output stream ending is not a reason to drop the session
+    fn finished(&mut self, _: &mut Self::Context) {}
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ConsoleSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let text = match msg {
+            Ok(ws::Message::Text(text)) => text.to_string(),
+            Ok(ws::Message::Ping(p)) => return ctx.pong(&p),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                return ctx.stop();
+            },
+            Ok(_) => return,
+            Err(e) => {
+                log::error!("console session {} failed: {}", self.id, e);
+                return ctx.stop();
+            }
+        };
+
+        if !self.authorized {
+            if text != self.password {
+                log::error!("wrong password for console session {}", self.id);
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Policy,
+                    description: Some("wrong password".to_owned()),
+                }));
+                return ctx.stop();
+            }
+
+            self.authorized = true;
+
+            self.instance.send(instance_messages::ConsoleSubscribe)
+                .into_actor(self)
+                .map(|res, _, ctx| match res {
+                    Ok(stream) => {
+                        ctx.add_stream(stream);
+                    },
+                    Err(e) => {
+                        log::error!("cannot subscribe to console: {}", e);
+                        ctx.stop();
+                    }
+                })
+                .wait(ctx);
+            return;
+        }
+
+        self.instance.send(instance_messages::ConsoleInput { session: self.id, line: text, caller: self.caller.clone() })
+            .into_actor(self)
+            .map(|res, _, ctx| {
+                let res = res.map_err(anyhow::Error::from).and_then(|r| r);
+                if let Err(e) = res {
+                    ctx.text(format!("error: {}", e));
+                }
+            })
+            .wait(ctx);
+    }
+}
+
+/// `/console_ws?name=`, sessions are viewers until they write, the first writer keeps the console till it leaves
+pub async fn console_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    info: web::Query<ConsoleParams>,
+    native: web::Data<native::Service>,
+    password: web::Data<AdminPassword>,
+) -> actix_web::Result<HttpResponse> {
+    let Some(instance) = native.send(native_messages::AddrOf::new(info.name.clone()))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)? else {
+        return Err(actix_web::error::ErrorNotFound("no such server"));
+    };
+
+    let session = ConsoleSession {
+        id: SESSIONS.fetch_add(1, Ordering::Relaxed),
+        instance,
+        caller: req.peer_addr().map(|a| a.ip().to_string()),
+        password: password.0.clone(),
+        authorized: false,
+    };
+
+    ws::start(session, &req, stream)
+}
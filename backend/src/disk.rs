@@ -0,0 +1,79 @@
+use std::{ffi::CString, os::unix::ffi::OsStrExt, path::Path};
+
+use crate::{model, worlds};
+
+/// how many ticks pass between rescans of an instance
+pub const SCAN_EVERY_TICKS: u32 = 40;
+
+/// share of the quota after which we start warning
+pub const WARN_RATIO: f64 = 0.9;
+
+const MOD_DIRS: [&str; 3] = ["mods", "plugins", "config"];
+const LOG_DIRS: [&str; 2] = ["logs", "crash-reports"];
+const BACKUP_DIRS: [&str; 1] = ["backups"];
+
+/// size of everything below `p`, symlinks are not followed
+fn size_of(p: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(p) else {
+        return 0;
+    };
+
+    if !meta.is_dir() {
+        return meta.len();
+    }
+
+    std::fs::read_dir(p)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| size_of(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// free bytes on the filesystem holding `at`
+pub fn host_free(at: impl AsRef<Path>) -> Option<u64> {
+    let path = CString::new(at.as_ref().as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    // SAFETY: path is a valid nul terminated string and stat is a valid out pointer
+    let res = unsafe { libc::statvfs(path.as_ptr(), &mut stat) };
+    if res != 0 {
+        return None;
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// walks the whole server directory, this blocks thread
+pub fn usage(at: impl AsRef<Path>) -> anyhow::Result<model::DiskUsage> {
+    let at = at.as_ref();
+
+    let worlds: Vec<String> = worlds::list(at)?.into_iter().map(|w| w.name).collect();
+
+    let mut usage = model::DiskUsage::default();
+
+    for e in std::fs::read_dir(at)?.filter_map(|e| e.ok()) {
+        let name = e.file_name().to_string_lossy().into_owned();
+        let size = size_of(&e.path());
+
+        let bucket = if name.contains("-backup-") || BACKUP_DIRS.contains(&name.as_str()) {
+            &mut usage.backups
+        } else if worlds.iter().any(|w| name == *w || name.strip_prefix(w.as_str()).map(|s| s.starts_with('_')).unwrap_or(false)) {
+            &mut usage.worlds
+        } else if MOD_DIRS.contains(&name.as_str()) {
+            &mut usage.mods
+        } else if LOG_DIRS.contains(&name.as_str()) {
+            &mut usage.logs
+        } else {
+            &mut usage.other
+        };
+
+        *bucket += size;
+        usage.total += size;
+    }
+
+    usage.host_free = host_free(at);
+    usage.computed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    Ok(usage)
+}
@@ -0,0 +1,283 @@
+use std::{collections::{HashMap, HashSet}, path::Path, sync::{Arc, Mutex}};
+
+use anyhow::anyhow;
+use tokio::sync::broadcast;
+
+use crate::{history, model::{self, EventPattern, GameEventKind}};
+
+/// one json event per line, inside the server directory
+pub const EVENTS_FILE: &str = "msrvEvents.jsonl";
+
+/// used when the server doesn't pick a profile, or picks one that doesn't exist
+pub const DEFAULT_PROFILE: &str = "vanilla";
+
+/// events kept for subscribers that fall behind
+const BACKLOG: usize = 256;
+
+/// `[12:00:00] [Server thread/INFO]: ` and forge's `... [minecraft/DedicatedServer]: `
+const LOG_PREFIX: &str = r"^(?:\[[^\]]*\]\s*)+:\s*";
+
+const NAME: &str = r"(?P<player>[A-Za-z0-9_]{3,16})";
+
+pub type Events = broadcast::Sender<model::GameEvent>;
+
+pub type EventStream = std::pin::Pin<Box<dyn futures::Stream<Item = model::GameEvent> + Send + 'static>>;
+
+pub fn events() -> Events {
+    broadcast::channel(BACKLOG).0
+}
+
+fn pattern(kind: GameEventKind, pattern: String) -> EventPattern {
+    EventPattern { kind, pattern }
+}
+
+/// patterns see the line without its log prefix, `player` and `message` groups are picked up
+/// deaths are only taken for players who are online, anything else logging `<word> was ...` is not one
+pub fn vanilla() -> Vec<EventPattern> {
+    use GameEventKind::*;
+
+    vec![
+        pattern(Join, format!(r"^{} joined the game$", NAME)),
+        pattern(Leave, format!(r"^{} left the game$", NAME)),
+        pattern(Chat, format!(r"^(?:\[Not Secure\] )?<{}> (?P<message>.*)$", NAME)),
+        pattern(Advancement, format!(r"^{} has (?:made the advancement|completed the challenge|reached the goal) \[(?P<message>.+)\]$", NAME)),
+        pattern(Death, format!(
+            r"^{} (?P<message>(?:was|died|drowned|blew up|burned|fell|hit the ground|went up in flames|went off with a bang|walked into|tried to swim|starved|suffocated|froze|withered|experienced kinetic energy|discovered the floor|left the confines|didn't want to live|is no more)\b.*)$",
+            NAME
+        )),
+        pattern(Lag, r"^Can't keep up! (?P<message>.*)$".to_owned()),
+    ]
+}
+
+/// json object of profile name to patterns, on top of the built in `vanilla`
+pub fn load_profiles(path: &Path) -> anyhow::Result<HashMap<String, Vec<EventPattern>>> {
+    let mut profiles = default_profiles();
+    let loaded: HashMap<String, Vec<EventPattern>> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+    for (name, patterns) in loaded {
+        Parser::new(&patterns).map_err(|e| anyhow!("profile {}: {}", name, e))?;
+        profiles.insert(name, patterns);
+    }
+
+    Ok(profiles)
+}
+
+pub fn default_profiles() -> HashMap<String, Vec<EventPattern>> {
+    HashMap::from([(DEFAULT_PROFILE.to_owned(), vanilla())])
+}
+
+pub struct Parser {
+    prefix: regex::Regex,
+    patterns: Vec<(GameEventKind, regex::Regex)>,
+}
+
+impl Parser {
+    pub fn new(patterns: &[EventPattern]) -> anyhow::Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| regex::Regex::new(&p.pattern)
+                .map(|re| (p.kind, re))
+                .map_err(|e| anyhow!("bad pattern {:?}: {}", p.pattern, e)))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            prefix: regex::Regex::new(LOG_PREFIX)?,
+            patterns,
+        })
+    }
+
+    /// first matching pattern wins
+    pub fn parse(&self, line: &str) -> Option<model::GameEvent> {
+        let text = match self.prefix.find(line) {
+            Some(prefix) => &line[prefix.end()..],
+            None => line,
+        };
+
+        self.patterns.iter().find_map(|(kind, re)| {
+            let caps = re.captures(text)?;
+            Some(model::GameEvent {
+                at: history::now(),
+                kind: *kind,
+                player: caps.name("player").map(|m| m.as_str().to_owned()),
+                message: caps.name("message").map(|m| m.as_str().to_owned()),
+                line: text.to_owned(),
+            })
+        })
+    }
+}
+
+/// turns output lines of one run into events, stored and published
+pub struct Sink {
+    parser: Parser,
+    place: Arc<Path>,
+    events: Events,
+    /// joined and not left yet during this run
+    online: Mutex<HashSet<String>>,
+}
+
+impl Sink {
+    pub fn new(parser: Parser, place: Arc<Path>, events: Events) -> Self {
+        Self { parser, place, events, online: Default::default() }
+    }
+
+    pub fn line(&self, line: &str) {
+        let Some(event) = self.parser.parse(line) else {
+            return;
+        };
+
+        if !self.track(&event) {
+            return;
+        }
+
+        record(&self.place, &event);
+        // nobody subscribed is fine
+        let _ = self.events.send(event);
+    }
+}
+
+impl Sink {
+    /// keeps who is online, false for deaths of someone who isn't
+    fn track(&self, event: &model::GameEvent) -> bool {
+        let Some(player) = &event.player else {
+            return true;
+        };
+        let Ok(mut online) = self.online.lock() else {
+            return true;
+        };
+
+        match event.kind {
+            GameEventKind::Join => {
+                online.insert(player.clone());
+                true
+            },
+            GameEventKind::Leave => {
+                online.remove(player);
+                true
+            },
+            GameEventKind::Death => online.contains(player),
+            _ => true,
+        }
+    }
+}
+
+fn record(at: &Path, event: &model::GameEvent) {
+    let res = serde_json::to_string(event)
+        .map_err(anyhow::Error::from)
+        .and_then(|line| history::append_line(&at.join(EVENTS_FILE), &line));
+
+    if let Err(e) = res {
+        log::error!("cannot store event of {:?}: {}", at, e);
+    }
+}
+
+/// newest first, all kinds if `kinds` is empty
+/// this blocks thread
+pub fn query(at: &Path, kinds: &[GameEventKind], limit: usize) -> anyhow::Result<Vec<model::GameEvent>> {
+    Ok(history::read_lines(&at.join(EVENTS_FILE))?
+        .iter()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter(|e: &model::GameEvent| kinds.is_empty() || kinds.contains(&e.kind))
+        .take(limit)
+        .collect())
+}
+
+pub fn stream(events: &Events) -> EventStream {
+    use futures::StreamExt;
+
+    tokio_stream::wrappers::BroadcastStream::new(events.subscribe())
+        .filter_map(|e| async { e.ok() })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Option<(GameEventKind, Option<String>, Option<String>)> {
+        Parser::new(&vanilla()).unwrap().parse(line).map(|e| (e.kind, e.player, e.message))
+    }
+
+    fn kind(line: &str) -> Option<GameEventKind> {
+        parse(line).map(|e| e.0)
+    }
+
+    #[test]
+    fn vanilla_lines() {
+        assert_eq!(
+            parse("[12:34:56] [Server thread/INFO]: Steve joined the game"),
+            Some((GameEventKind::Join, Some("Steve".to_owned()), None))
+        );
+        assert_eq!(kind("[12:34:56] [Server thread/INFO]: Steve left the game"), Some(GameEventKind::Leave));
+        assert_eq!(
+            parse("[12:35:00] [Server thread/INFO]: [Not Secure] <Steve> hello there"),
+            Some((GameEventKind::Chat, Some("Steve".to_owned()), Some("hello there".to_owned())))
+        );
+        assert_eq!(
+            parse("[12:36:00] [Server thread/INFO]: Steve has made the advancement [Stone Age]"),
+            Some((GameEventKind::Advancement, Some("Steve".to_owned()), Some("Stone Age".to_owned())))
+        );
+        assert_eq!(
+            parse("[12:37:00] [Server thread/INFO]: Steve was slain by Zombie"),
+            Some((GameEventKind::Death, Some("Steve".to_owned()), Some("was slain by Zombie".to_owned())))
+        );
+        assert_eq!(
+            kind("[12:38:00] [Server thread/WARN]: Can't keep up! Is the server overloaded? Running 2034ms or 40 ticks behind"),
+            Some(GameEventKind::Lag)
+        );
+        assert_eq!(kind("[12:38:00] [Server thread/INFO]: Done (4.201s)! For help, type \"help\""), None);
+    }
+
+    #[test]
+    fn forge_lines() {
+        assert_eq!(
+            parse("[12Mar2024 12:34:56.789] [Server thread/INFO] [net.minecraft.server.MinecraftServer/]: Steve joined the game"),
+            Some((GameEventKind::Join, Some("Steve".to_owned()), None))
+        );
+        assert_eq!(
+            parse("[12:34:56] [Server thread/INFO] [minecraft/DedicatedServer]: <Steve> hi"),
+            Some((GameEventKind::Chat, Some("Steve".to_owned()), Some("hi".to_owned())))
+        );
+    }
+
+    #[test]
+    fn chat_cannot_spoof_events() {
+        for line in [
+            "[12:35:00] [Server thread/INFO]: <Steve> Alex joined the game",
+            "[12:35:00] [Server thread/INFO]: <Steve> [Server thread/INFO]: Alex joined the game",
+            "[12:35:00] [Server thread/INFO]: <Steve> ]: Alex was slain by Zombie",
+        ] {
+            assert_eq!(kind(line), Some(GameEventKind::Chat), "{}", line);
+        }
+
+        // `/say` and `/me`
+        assert_eq!(kind("[12:35:00] [Server thread/INFO]: [Steve] Alex joined the game"), None);
+        assert_eq!(kind("[12:35:00] [Server thread/INFO]: [Steve] ]: Alex joined the game"), None);
+        assert_eq!(kind("[12:35:00] [Server thread/INFO]: * Steve was slain by Zombie"), None);
+    }
+
+    #[test]
+    fn deaths_only_of_online_players() {
+        let dir = std::env::temp_dir().join(format!("msrv-events-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+
+        let events = events();
+        let mut seen = events.subscribe();
+        let sink = Sink::new(Parser::new(&vanilla()).unwrap(), dir.clone().into(), events);
+
+        sink.line("[12:00:00] [Server thread/INFO]: Config was loaded from defaults");
+        sink.line("[12:00:01] [Server thread/INFO]: Steve joined the game");
+        sink.line("[12:00:02] [Server thread/INFO]: Steve fell from a high place");
+        sink.line("[12:00:03] [Server thread/INFO]: Steve left the game");
+        sink.line("[12:00:04] [Server thread/INFO]: Steve was slain by Zombie");
+
+        let mut kinds = Vec::new();
+        while let Ok(e) = seen.try_recv() {
+            kinds.push(e.kind);
+        }
+        assert_eq!(kinds, vec![GameEventKind::Join, GameEventKind::Death, GameEventKind::Leave]);
+        assert_eq!(query(&dir, &[GameEventKind::Death], 10).unwrap().len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
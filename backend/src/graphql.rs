@@ -33,6 +33,166 @@ impl Query {
         Ok(service.send(native_messages::Ports).await?)
     }
 
+    async fn mods<'cx>(&self, ctx: &Context<'cx>, name: String) -> anyhow::Result<Vec<model::ModInfo>> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::Mods).await?
+    }
+
+    async fn preflight<'cx>(&self, ctx: &Context<'cx>, name: String) -> anyhow::Result<model::PreflightReport> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::Preflight).await?
+    }
+
+    async fn worlds<'cx>(&self, ctx: &Context<'cx>, name: String) -> anyhow::Result<Vec<model::WorldInfo>> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::Worlds).await?
+    }
+
+    /// deleted servers waiting to be purged
+    async fn list_trash<'cx>(&self, ctx: &Context<'cx>) -> anyhow::Result<Vec<model::TrashEntry>> {
+        let service = ctx.data_unchecked::<native::Service>();
+        Ok(service.send(native_messages::ListTrash).await?)
+    }
+
+    /// descriptor a broken server would be repaired with
+    async fn repair_proposal<'cx>(&self, ctx: &Context<'cx>, name: String) -> anyhow::Result<Option<model::RepairProposal>> {
+        let service = ctx.data_unchecked::<native::Service>();
+        Ok(service.send(native_messages::ProposeRepair { name }).await?)
+    }
+
+    /// directories moved aside on startup for having no manifest
+    async fn quarantined<'cx>(&self, ctx: &Context<'cx>) -> anyhow::Result<Vec<model::QuarantinedServer>> {
+        let service = ctx.data_unchecked::<native::Service>();
+        Ok(service.send(native_messages::ListQuarantined).await?)
+    }
+
+    /// players on a running server
+    async fn online_players<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<model::OnlinePlayers> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::OnlinePlayers).await?
+    }
+
+    /// token to put into `/client_pack?name=<name>&token=<token>`, it only allows downloading the pack
+    async fn client_pack_token<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<String> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::PackToken { rotate: false }).await?
+    }
+
+    /// whitelist, ops and bans with their reasons
+    async fn player_lists<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<model::PlayerLists> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::PlayerLists).await?
+    }
+
+    /// stored events of the server, newest first
+    async fn instance_event_history<'cx>(&self, ctx: &Context<'cx>, name: String, #[graphql(default)] kinds: Vec<model::GameEventKind>, limit: Option<usize>, password: String) -> anyhow::Result<Vec<model::GameEvent>> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::EventHistory {
+            kinds,
+            limit: limit.unwrap_or(history::DEFAULT_LIMIT)
+        }).await?
+    }
+
+    /// commands sent to the server, newest first
+    async fn command_history<'cx>(&self, ctx: &Context<'cx>, name: String, search: Option<String>, limit: Option<usize>, password: String) -> anyhow::Result<Vec<model::CommandRecord>> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::History {
+            search,
+            limit: limit.unwrap_or(history::DEFAULT_LIMIT)
+        }).await?
+    }
+
     //todo: add here names someday
     async fn rcons<'cx>(&self, ctx: &Context<'cx>) -> serde_json::Value {
         let service = ctx.data_unchecked::<native::Service>();
@@ -58,7 +218,10 @@ pub struct ServerData {
     // setup_cmd: Option<String>,
     url: url::Url,
     max_memory: f64,
-    ports: model::Ports,
+    /// allocated by the manager when left out
+    ports: Option<model::PortsRequest>,
+    /// profile the log is parsed with, `vanilla` when left out
+    event_profile: Option<String>,
 }
 
 #[Object]
@@ -67,7 +230,8 @@ impl Mutation {
     async fn should_run<'cx>(&self,
         ctx: &Context<'cx>,
         name: String,
-        should_run: bool
+        should_run: bool,
+        force: Option<bool>
     ) -> Result<bool,anyhow::Error> {
         let service = ctx.data_unchecked::<native::Service>();
 
@@ -77,7 +241,8 @@ impl Mutation {
         
         if let Some(addr) = addr {
             addr.send(instance_messages::SwitchServer {
-                should_run
+                should_run,
+                force: force.unwrap_or(false)
             }).await??;
             return Ok(true)
         } else {
@@ -119,9 +284,7 @@ impl Mutation {
         //     return Err(anyhow::anyhow!("server_jar must be a relative path"));
         // }
 
-        if name.is_empty() || name.contains('/') {
-            return Err(anyhow::anyhow!("name must not be empty, or contain '/'"));
-        }
+        utils::validate_server_name(&name)?;
 
         let val = upload.value(ctx)?;
         
@@ -130,7 +293,8 @@ impl Mutation {
             // setup_cmd: data.setup_cmd,
             url: data.url,
             max_memory: data.max_memory,
-            ports: data.ports,
+            ports: data.ports.unwrap_or_default(),
+            event_profile: data.event_profile,
             ext: native::NewServer(name,val),
             java_args: java_args_transform(data.java_args)
         }).await??;
@@ -139,6 +303,7 @@ impl Mutation {
 
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn alter_server<'cx>(
         &self,
         ctx: &Context<'cx>,
@@ -146,6 +311,13 @@ impl Mutation {
         max_memory: Option<f64>,
         java_args: Option<String>,
         port: Option<u16>,
+        rcon: Option<u16>,
+        extra_ports: Option<Vec<model::ExtraPortRequest>>,
+        remove_ports: Option<Vec<String>>,
+        hostnames: Option<Vec<String>>,
+        disk_quota: Option<f64>,
+        stop_on_quota: Option<bool>,
+        event_profile: Option<String>,
         password: String
     ) -> Result<bool,anyhow::Error> {
 
@@ -160,10 +332,20 @@ impl Mutation {
 
         service.send(native_messages::AlterServer {
             name: name.clone(),
+            ports: model::PortsChange {
+                port,
+                rcon,
+                extra: extra_ports.unwrap_or_default(),
+                remove_extra: remove_ports.unwrap_or_default(),
+            },
             msg: instance_messages::AlterServer {
                 max_memory,
                 java_args: java_args.map(java_args_transform),
-                port
+                ports: None,
+                hostnames,
+                disk_quota,
+                stop_on_quota,
+                event_profile
             }
         }).await??;
 
@@ -186,7 +368,52 @@ impl Mutation {
         Ok(true)
     }
 
-    async fn rcon_message<'cx>(&self,ctx: &Context<'cx>,name: String, message: String, password: String) -> Result<bool,anyhow::Error> {
+    /// the server has to be stopped
+    async fn rename_server<'cx>(&self, ctx: &Context<'cx>, old: String, new: String, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        service.send(native_messages::RenameServer { old, new }).await??;
+        Ok(true)
+    }
+
+    async fn restore_deleted<'cx>(&self, ctx: &Context<'cx>, id: String, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        service.send(native_messages::RestoreDeleted { id }).await??;
+        Ok(true)
+    }
+
+    /// removes deleted server for good, or all of them without an id
+    async fn purge_trash<'cx>(&self, ctx: &Context<'cx>, id: Option<String>, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        service.send(native_messages::PurgeTrash { id }).await??;
+        Ok(true)
+    }
+
+    /// kick needs the server running, the rest edit its lists when it is stopped
+    async fn player_action<'cx>(&self, ctx: &Context<'cx>, name: String, player: String, action: model::PlayerAction, reason: Option<String>, password: String) -> anyhow::Result<model::PlayerActionResult> {
         let service = ctx.data_unchecked::<native::Service>();
 
         let pass = ctx.data_unchecked::<Password>();
@@ -202,10 +429,279 @@ impl Mutation {
             return Err(anyhow::anyhow!("no such server: {}",name));
         };
 
-        addr.send(rcon::RconMessage {
-            cmd: message
+        addr.send(instance_messages::PlayerAction {
+            player,
+            action,
+            reason,
+            origin: model::CommandOrigin::Api,
+            caller: caller(ctx)
+        }).await?
+    }
+
+    /// returns the new secret, it takes effect when the server is restarted
+    async fn rotate_rcon_password<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<String> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::RotateRconPassword).await?
+    }
+
+    /// returns the new client pack token, links with the old one stop working
+    async fn rotate_client_pack_token<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<String> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::PackToken { rotate: true }).await?
+    }
+
+    /// rules checked before the global ones, empty list removes them
+    async fn set_command_rules<'cx>(&self, ctx: &Context<'cx>, name: String, rules: Vec<model::CommandRule>, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::SetCommandRules { rules }).await??;
+        Ok(true)
+    }
+
+    /// output of the command, it is also published to `rconOutput`
+    async fn rcon_message<'cx>(&self,ctx: &Context<'cx>,name: String, message: String, password: String) -> Result<String,anyhow::Error> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        let response = addr.send(rcon::RconMessage {
+            cmd: message,
+            origin: model::CommandOrigin::Api,
+            caller: caller(ctx)
+        }).await??;
+
+        Ok(response)
+    }
+
+    async fn upload_world<'cx>(&self, ctx: &Context<'cx>, name: String, world: String, upload: Upload, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        worlds::validate_name(&world)?;
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::UploadWorld {
+            name: world,
+            payload: upload.value(ctx)?
+        }).await??;
+
+        Ok(true)
+    }
+
+    async fn switch_world<'cx>(&self, ctx: &Context<'cx>, name: String, world: String, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::SwitchWorld { name: world }).await??;
+
+        Ok(true)
+    }
+
+    async fn delete_world<'cx>(&self, ctx: &Context<'cx>, name: String, world: String, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::DeleteWorld { name: world }).await??;
+
+        Ok(true)
+    }
+
+    /// returns name of the world the old one was kept as
+    async fn reset_world<'cx>(
+        &self,
+        ctx: &Context<'cx>,
+        name: String,
+        seed: Option<String>,
+        level_type: Option<String>,
+        password: String
+    ) -> anyhow::Result<Option<String>> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        addr.send(instance_messages::ResetWorld { seed, level_type }).await?
+    }
+
+    /// turns a quarantined directory back into a server, ports and memory default to what its files say
+    /// it is named after the directory unless `new_name` is given
+    #[allow(clippy::too_many_arguments)]
+    async fn adopt_server<'cx>(
+        &self,
+        ctx: &Context<'cx>,
+        name: String,
+        url: url::Url,
+        port: Option<u16>,
+        rcon: Option<u16>,
+        max_memory: Option<f64>,
+        java_args: Option<String>,
+        new_name: Option<String>,
+        password: String
+    ) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(found) = service.send(native_messages::ListQuarantined).await?
+            .into_iter()
+            .find(|q| q.name == name) else {
+            return Err(anyhow::anyhow!("no such quarantined directory: {}",name));
+        };
+
+        let (Some(port), Some(rcon)) = (port.or(found.port), rcon.or(found.rcon)) else {
+            return Err(anyhow::anyhow!("couldn't infer ports, specify them explicitly"));
+        };
+
+        let Some(max_memory) = max_memory.or(found.max_memory) else {
+            return Err(anyhow::anyhow!("couldn't infer max memory, specify it explicitly"));
+        };
+
+        service.send(native_messages::InitServer {
+            java_args: java_args.map(java_args_transform).unwrap_or(found.java_args),
+            url,
+            max_memory,
+            ports: model::PortsRequest { port: Some(port), rcon: Some(rcon), extra: Vec::new() },
+            event_profile: None,
+            ext: native::AdoptServer(name.clone(), new_name.unwrap_or(name))
         }).await??;
+        Ok(true)
+    }
+
+    /// renews a broken server with its repair proposal
+    async fn repair_server<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<bool> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
 
+        let Some(proposal) = service.send(native_messages::ProposeRepair { name: name.clone() }).await? else {
+            return Err(anyhow::anyhow!("no such broken server: {}",name));
+        };
+
+        let (Some(url), Some(max_memory), Some(port), Some(rcon), true) = (
+            proposal.url,
+            proposal.max_memory,
+            proposal.port,
+            proposal.rcon,
+            proposal.problems.is_empty()
+        ) else {
+            return Err(anyhow::anyhow!("cannot repair automatically: {}", proposal.problems.join(", ")));
+        };
+
+        service.send(native_messages::InitServer {
+            java_args: proposal.java_args,
+            url,
+            max_memory,
+            ports: model::PortsRequest { port: Some(port), rcon: Some(rcon), extra: Vec::new() },
+            event_profile: None,
+            ext: native::ReNewServer(name)
+        }).await??;
         Ok(true)
     }
 
@@ -225,7 +721,8 @@ impl Mutation {
             // setup_cmd: data.setup_cmd,
             url: data.url,
             max_memory: data.max_memory,
-            ports: data.ports,
+            ports: data.ports.unwrap_or_default(),
+            event_profile: data.event_profile,
             ext: native::ReNewServer(name)
 
         }).await??;
@@ -258,18 +755,20 @@ impl Subscription {
                 f: |i| Some((
                     i.desc().cloned(),
                     i.state(),
-                    i.name()
+                    i.name(),
+                    i.disk().cloned()
                 ))
             }).await {
                 Ok(data) => {
                     let data = data
                         .into_iter()
-                        .map(|(desc,state,place)| {
+                        .map(|(desc,state,place,disk)| {
                             (
                                 place,
                                 serde_json::json!({
                                     "data": desc,
-                                    "state": state
+                                    "state": state,
+                                    "disk": disk
                                 })
                             )
                         })
@@ -325,6 +824,26 @@ impl Subscription {
         Ok(stream)
     }
 
+    /// events parsed from the server log as they happen
+    async fn instance_events<'cx>(&self, ctx: &Context<'cx>, name: String, password: String) -> anyhow::Result<events::EventStream> {
+        let service = ctx.data_unchecked::<native::Service>();
+
+        let pass = ctx.data_unchecked::<Password>();
+
+        if pass.0 != password {
+            log::error!("wrong password: {}",password);
+            return Err(anyhow::anyhow!("wrong password"));
+        }
+
+        let Some(addr) = service.send(
+            native_messages::AddrOf::new(name.clone())
+        ).await? else {
+            return Err(anyhow::anyhow!("no such server: {}",name));
+        };
+
+        Ok(addr.send(instance_messages::EventSubscribe).await?)
+    }
+
     async fn rcon_output<'cx>(&self, ctx: &Context<'cx>, name: String) -> anyhow::Result<RconStream> {
         let service = ctx.data_unchecked::<native::Service>();
 
@@ -337,9 +856,7 @@ impl Subscription {
         let stream = addr.send(rcon::RconSubscription).await??;
 
         let stream = stream
-            .map(move |i| (i,addr.clone()))
-            //rewrite with unfold to terminate subscription
-            .filter_map(|(out,addr)| async move {
+            .filter_map(|out| async move {
                 match out {
                     rcon::RconOutput::CommandResponse(resp) => {
                         Some(resp)
@@ -348,10 +865,8 @@ impl Subscription {
                         log::error!("rcon error: {}",error);
                         None
                     },
-                    rcon::RconOutput::ConnectionClosed => {
-                        addr.send(rcon::RconDown).await.unwrap();
-                        None
-                    },
+                    // instance reconnects by itself, this stream ends with the old connection
+                    rcon::RconOutput::ConnectionClosed => None,
                 }
             })
             .map({
@@ -398,6 +913,13 @@ pub type SrvsSchema = Schema<Query, Mutation, Subscription>;
 
 struct Password(String);
 
+/// peer address of the http request, put into request data by the endpoint
+pub struct Caller(pub Option<String>);
+
+fn caller(ctx: &Context<'_>) -> Option<String> {
+    ctx.data_opt::<Caller>().and_then(|c| c.0.clone())
+}
+
 pub fn schema(addr: crate::native::Service,pass: String) -> SrvsSchema {
     Schema::build(Query,Mutation, Subscription)
     .data::<native::Service>(addr)
@@ -0,0 +1,113 @@
+use std::{io::{BufRead, BufReader, Write}, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::model;
+
+/// one json record per line, inside the server directory
+pub const HISTORY_FILE: &str = "msrvHistory.jsonl";
+
+/// how many records a query returns when no limit is given
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// past this the file is moved aside to `<file>.1`, replacing the one before
+pub const ROTATE_BYTES: u64 = 4 * 1024 * 1024;
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn rotated(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".1");
+    name.into()
+}
+
+/// appends a line, so at most twice `ROTATE_BYTES` is kept
+pub fn append_line(file: &Path, line: &str) -> anyhow::Result<()> {
+    if std::fs::metadata(file).map(|m| m.len() >= ROTATE_BYTES).unwrap_or(false) {
+        std::fs::rename(file, rotated(file))?;
+    }
+
+    let mut out = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)?;
+    writeln!(out, "{}", line)?;
+    Ok(())
+}
+
+/// lines of the rotated file and then of the current one, oldest first
+/// this blocks thread
+pub fn read_lines(file: &Path) -> anyhow::Result<Vec<String>> {
+    let mut lines = Vec::new();
+
+    for path in [rotated(file), file.to_owned()] {
+        let opened = match std::fs::File::open(&path) {
+            Ok(opened) => opened,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        lines.extend(BufReader::new(opened).lines().map_while(Result::ok));
+    }
+
+    Ok(lines)
+}
+
+/// appends to the history, failures are only logged so commands still go through
+pub fn record(at: &Path, record: &model::CommandRecord) {
+    let res = serde_json::to_string(record)
+        .map_err(anyhow::Error::from)
+        .and_then(|line| append_line(&at.join(HISTORY_FILE), &line));
+
+    if let Err(e) = res {
+        log::error!("cannot record command in history of {:?}: {}", at, e);
+    }
+}
+
+/// newest first, `search` matches command or response ignoring case
+/// this blocks thread
+pub fn query(at: &Path, search: Option<&str>, limit: usize) -> anyhow::Result<Vec<model::CommandRecord>> {
+    let search = search.map(|s| s.to_lowercase());
+
+    let matches = |r: &model::CommandRecord| match &search {
+        Some(s) => r.command.to_lowercase().contains(s)
+            || r.response.as_ref().map(|resp| resp.to_lowercase().contains(s)).unwrap_or(false),
+        None => true,
+    };
+
+    Ok(read_lines(&at.join(HISTORY_FILE))?
+        .iter()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter(matches)
+        .take(limit)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_and_reads_both() {
+        let dir = std::env::temp_dir().join(format!("msrv-history-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let file = dir.join(HISTORY_FILE);
+
+        append_line(&file, "old").unwrap();
+        // pretend it grew past the limit
+        std::fs::OpenOptions::new().append(true).open(&file).unwrap().set_len(ROTATE_BYTES).unwrap();
+        append_line(&file, "new").unwrap();
+
+        assert!(rotated(&file).is_file());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new\n");
+
+        let lines = read_lines(&file).unwrap();
+        assert_eq!(lines.first().map(|l| l.as_str()), Some("old"));
+        assert_eq!(lines.last().map(|l| l.as_str()), Some("new"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
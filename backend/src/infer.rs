@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use crate::{instance, model, properties::ServerProperties};
+
+/// files launchers keep jvm arguments in, in order of trust
+const ARG_FILES: [&str; 3] = ["user_jvm_args.txt", "jvm_args.txt", "variables.txt"];
+
+/// start scripts which usually have `-Xmx` right in the java command
+const START_SCRIPTS: [&str; 6] = ["run.sh", "start.sh", "startserver.sh", "ServerStart.sh", "run.bat", "start.bat"];
+
+/// what we could learn about a server from files it has
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct Inferred {
+    pub port: Option<u16>,
+    pub rcon: Option<u16>,
+    // in GB
+    pub max_memory: Option<f64>,
+    pub java_args: Vec<String>,
+}
+
+impl Inferred {
+    pub fn quarantined(self, name: String) -> model::QuarantinedServer {
+        model::QuarantinedServer {
+            name,
+            port: self.port,
+            rcon: self.rcon,
+            max_memory: self.max_memory,
+            java_args: self.java_args,
+        }
+    }
+}
+
+/// old manifest fields win, whatever is missing or unparsable comes from the files
+pub fn propose(at: impl AsRef<Path>, had: Option<&serde_json::Value>) -> model::RepairProposal {
+    let inferred = infer(at);
+    let had = had.and_then(|v| v.as_object());
+
+    let field = |k: &str| had.and_then(|h| h.get(k));
+    let port_field = |k: &str| {
+        field("ports")
+            .and_then(|p| p.get(k))
+            .and_then(|p| p.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+    };
+
+    let java_args: Option<Vec<String>> = field("java_args")
+        .and_then(|a| serde_json::from_value(a.clone()).ok());
+
+    let mut proposal = model::RepairProposal {
+        url: field("mods").and_then(|u| u.as_str()).and_then(|u| u.parse().ok()),
+        java_args: java_args.unwrap_or(inferred.java_args),
+        max_memory: field("max_memory").and_then(|m| m.as_f64()).filter(|m| *m > 0.0).or(inferred.max_memory),
+        port: port_field("port").or(inferred.port),
+        rcon: port_field("rcon").or(inferred.rcon),
+        problems: Vec::new(),
+    };
+
+    if proposal.url.is_none() {
+        proposal.problems.push("no modpack url".to_owned());
+    }
+    if proposal.max_memory.is_none() {
+        proposal.problems.push("couldn't find max memory".to_owned());
+    }
+
+    proposal
+}
+
+/// `-Xmx4G` -> 4.0, `-Xmx4096M` -> 4.0
+fn parse_xmx(arg: &str) -> Option<f64> {
+    let v = arg.strip_prefix("-Xmx")?;
+    let (num, unit) = v.split_at(v.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(v.len()));
+    let num: f64 = num.parse().ok()?;
+    match unit.to_ascii_lowercase().as_str() {
+        "g" | "gb" => Some(num),
+        "m" | "mb" => Some(num / 1024.0),
+        "k" | "kb" => Some(num / 1024.0 / 1024.0),
+        "" => Some(num / 1024.0 / 1024.0 / 1024.0),
+        _ => None,
+    }
+}
+
+/// arguments from a file, `#` comments and `KEY=` prefixes of `variables.txt` stripped
+fn args_of(src: &str) -> Vec<String> {
+    src.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.starts_with('#') && !l.starts_with("::") && !l.to_ascii_lowercase().starts_with("rem "))
+        .flat_map(|l| {
+            let l = match l.split_once('=') {
+                Some((k, v)) if k.chars().all(|c| c.is_ascii_uppercase() || c == '_') => v.trim_matches('"'),
+                _ => l,
+            };
+            l.split_whitespace().map(|a| a.to_owned()).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// this blocks thread
+pub fn infer(at: impl AsRef<Path>) -> Inferred {
+    let at = at.as_ref();
+    let mut out = Inferred::default();
+
+    if let Ok(props) = ServerProperties::load(at.join(instance::SERVER_PROPERTIES_FILE)) {
+        out.port = props.get_parsed("server-port");
+        out.rcon = props.get_parsed("rcon.port");
+    }
+
+    for f in ARG_FILES.iter().chain(START_SCRIPTS.iter()) {
+        let Ok(src) = std::fs::read_to_string(at.join(f)) else {
+            continue;
+        };
+
+        let args = args_of(&src);
+
+        if out.max_memory.is_none() {
+            out.max_memory = args.iter().find_map(|a| parse_xmx(a));
+        }
+
+        // scripts have the whole command line, only arg files are worth copying
+        if out.java_args.is_empty() && ARG_FILES.contains(f) {
+            out.java_args = args
+                .into_iter()
+                .filter(|a| a.starts_with('-'))
+                .filter(|a| !a.starts_with("-Xmx") && !a.starts_with("-Xms"))
+                .collect();
+        }
+    }
+
+    out
+}
@@ -14,13 +14,14 @@ use std::process::Command;
 
 pub struct InstanceData {
     pub desc: model::InstanceDescriptor,
-    manifest: std::fs::File,
+    manifest: model::Manifest,
 }
 
 pub enum InstanceState {
+    /// rcon is none while it is being reconnected
     Running {
         child: Child,
-        rcon: rcon::Rcon,
+        rcon: Option<rcon::Rcon>,
         data: InstanceData
     },
     Starting {
@@ -36,7 +37,8 @@ pub enum InstanceState {
     Downloading {
         desc: model::InstanceDescriptor,
         // setup_cmd: Option<Command>,
-        payload: UploadValue
+        /// taken by the install task
+        payload: Option<UploadValue>
     },
     /// this state is blank, used for transactional operations
     Swap
@@ -45,7 +47,13 @@ pub enum InstanceState {
 pub struct InstanceEnv {
     pub servers: Addr<native::Servers>,
     pub timeout: std::time::Duration,
-    pub password: String
+    pub downloads: packs::DownloadSource,
+    /// in bytes
+    pub disk_reserve: u64,
+    /// apply to every server, after its own
+    pub command_rules: Arc<policy::RuleSet>,
+    /// log patterns by profile name
+    pub event_profiles: Arc<std::collections::HashMap<String, Vec<model::EventPattern>>>
 }
 
 /// The descriptor of a server
@@ -53,10 +61,31 @@ pub struct InstanceEnv {
 /// there should be `msrvDesc.json` file, manifest field
 pub struct Instance {
     /// should point at directory where Instance is or should be located located
+    /// named by the id of the instance
     place: Arc<Path>,
+    /// human readable, kept in the manifest
+    name: String,
     env: InstanceEnv,
 
     state: InstanceState,
+
+    mods: mods::ModCache,
+
+    disk: Option<model::DiskUsage>,
+    disk_scan_running: bool,
+    ticks: u32,
+    /// bumped on every start, rcon attempts of previous runs are ignored
+    rcon_epoch: u32,
+    /// secret the running process was started with, rotation doesn't change it
+    rcon_password: String,
+    /// output of the process, outlives restarts
+    console: console::Console,
+    /// console session allowed to write to stdin
+    console_writer: Option<u64>,
+    /// parsed from the output, outlives restarts
+    events: events::Events,
+    /// compiled `command_rules` of the descriptor
+    command_rules: policy::RuleSet,
 }
 
 impl Instance {
@@ -65,12 +94,30 @@ impl Instance {
     }
 
     pub fn name(&self) -> String {
-        self.place.file_name().unwrap().to_string_lossy().to_string()
+        self.name.clone()
+    }
+
+    /// updates manifest too, uniqueness is up to the caller
+    /// rcon, proxy routes and consoles are keyed by name, so the server has to be stopped
+    pub fn set_name(&mut self, name: String) -> anyhow::Result<()> {
+        match &mut self.state {
+            InstanceState::Crashed { data } |
+            InstanceState::Stopped { data } => {
+                data.desc.name = name.clone();
+                data.desc.flush(&mut data.manifest)?;
+            },
+            InstanceState::Swap => return Err(anyhow!("server {:?} is busy", &self.place)),
+            _ => return Err(anyhow!("server {:?} has to be stopped to be renamed", &self.place))
+        }
+
+        self.name = name;
+        Ok(())
     }
 
     pub fn state(&self) -> model::InstanceState {
         match self.state {
-            InstanceState::Running { .. } => model::InstanceState::Running,
+            InstanceState::Running { rcon: Some(_), .. } => model::InstanceState::Running,
+            InstanceState::Running { rcon: None, .. } => model::InstanceState::RconUnavailable,
             InstanceState::Starting { .. } => model::InstanceState::Starting,
             InstanceState::Crashed { .. } => model::InstanceState::Crashed,
             InstanceState::Stopped { .. } => model::InstanceState::Stopped,
@@ -79,6 +126,10 @@ impl Instance {
         }
     }
 
+    pub fn disk(&self) -> Option<&model::DiskUsage> {
+        self.disk.as_ref()
+    }
+
     pub fn desc(&self) -> Option<&model::InstanceDescriptor> {
         match &self.state {
             InstanceState::Running { data, .. } |
@@ -106,13 +157,14 @@ impl Instance {
         payload: UploadValue,
         env: InstanceEnv,
     ) -> Self {
+        let name = desc.name.clone();
         let state = InstanceState::Downloading {
             desc,
             // setup_cmd: cmd.map(utils::make_command),
-            payload
+            payload: Some(payload)
         };
 
-        Self {place: at, state, env}
+        Self {place: at, name, state, env, mods: Default::default(), disk: None, disk_scan_running: false, ticks: 0, rcon_epoch: 0, rcon_password: String::new(), console: console::console(), console_writer: None, events: events::events(), command_rules: Default::default()}
     }
 
     pub fn load(place: Arc<Path>, env: InstanceEnv ) -> Result<(Self,model::Ports),LoadError> {
@@ -123,21 +175,36 @@ impl Instance {
             return Err(LoadError::PathIsNotDir);
         }
 
-        let mut manifest = utils::open_manifest(&*place).map_err(|e| LoadError::NoManifest(e))?;
+        let mut manifest = model::Manifest::at(&*place);
+        if !manifest.exists() {
+            return Err(LoadError::NoManifest(std::io::ErrorKind::NotFound.into()));
+        }
         let desc: model::InstanceDescriptor = model::InstanceDescriptor::from_file(&mut manifest).map_err(|e| LoadError::BadManifest(e))?;
 
-        let ports = desc.ports;
+        let ports = desc.ports.clone();
+        let command_rules = policy::RuleSet::or_deny_all(&desc.command_rules);
 
         Ok((
             Self {
                 place, 
+                name: desc.name.clone(),
                 state: InstanceState::Stopped {
                     data: InstanceData {
                         desc,
                         manifest
                     }
                 },
-                env
+                env,
+                mods: Default::default(),
+                disk: None,
+                disk_scan_running: false,
+                ticks: 0,
+                rcon_epoch: 0,
+                rcon_password: String::new(),
+                console: console::console(),
+                console_writer: None,
+                events: events::events(),
+                command_rules
             },
             ports    
         ))
@@ -149,9 +216,12 @@ impl Actor for Instance {
 
     fn started(&mut self, ctx: &mut Self::Context) {
 
-        let state = match &mut self.state {
+        let mut payload = match &mut self.state {
             // we whould start downloading
-            state @ InstanceState::Downloading { .. } => std::mem::replace(state, InstanceState::Swap),
+            InstanceState::Downloading { payload, .. } => match payload.take() {
+                Some(payload) => payload,
+                None => return,
+            },
             // it's fine
             InstanceState::Stopped { .. } | InstanceState::Crashed { .. } => return,
             _ => {
@@ -160,46 +230,64 @@ impl Actor for Instance {
                 return
             }
         };
-        
-        match state {
-            InstanceState::Downloading {  desc,mut payload, } => { // setup_cmd
-                if let Err(e) = utils::initialize_server_directory(&self.place,|| {
-                    Ok(utils::unpack_at(&self.place, &mut payload)?)
-                }) {
+
+        let place = Arc::clone(&self.place);
+        let downloads = self.env.downloads.clone();
+
+        // downloads and hashing block, the state stays downloading till they are done
+        let install = tokio::task::spawn_blocking(move || {
+            utils::initialize_server_directory(&place, || packs::install(&place, &mut payload, &downloads))
+        });
+
+        ctx.spawn(install.into_actor(self).map(|res, this, ctx| {
+            let pack = match res.map_err(anyhow::Error::from).and_then(|r| r) {
+                Ok(pack) => pack,
+                Err(e) => {
                     log::error!("cannot initialize server directory: {:?}",e);
                     ctx.stop();
                     return;
-                };
+                }
+            };
 
-                let mut data = InstanceData {
-                    desc,
-                    manifest: utils::open_manifest(&self.place).unwrap()
-                };
+            let desc = match std::mem::replace(&mut this.state, InstanceState::Swap) {
+                InstanceState::Downloading { desc, .. } => desc,
+                state => {
+                    log::error!("server {:?} left downloading state during install", &this.place);
+                    this.state = state;
+                    return;
+                }
+            };
 
-                data.desc.flush(&mut data.manifest).unwrap();
+            let mut data = InstanceData {
+                desc,
+                manifest: model::Manifest::at(&this.place)
+            };
 
-                self.state = InstanceState::Stopped { data };
+            data.desc.pack = pack;
 
-                // if let Some(mut cmd) = setup_cmd {
-                //     let output = cmd.output().unwrap();
-                //     if !output.status.success() {
-                //         log::error!("setup command failed with status: {}", output.status);
-                //         drop(data);
-                //         self.env.servers.do_send(native_messages::Nuke { who: Arc::clone(&self.place) });
-
-                //         ctx.stop();
-                //         return;
-                //     } else {
-                //         log::trace!("setup command executed successfully");
-                //         self.state = InstanceState::Stopped { data };
-                //     }
-                // } else {
-                //     self.state = InstanceState::Stopped { data };
-                // }
-            },
-            InstanceState::Crashed { .. } | InstanceState::Stopped { .. } => return,
-            _ => unreachable!()
-        }
+            data.desc.flush(&mut data.manifest).unwrap();
+
+            this.state = InstanceState::Stopped { data };
+
+            // if let Some(mut cmd) = setup_cmd {
+            //     let output = cmd.output().unwrap();
+            //     if !output.status.success() {
+            //         log::error!("setup command failed with status: {}", output.status);
+            //         drop(data);
+            //         self.env.servers.do_send(native_messages::Nuke { who: Arc::clone(&self.place) });
+
+            //         ctx.stop();
+            //         return;
+            //     } else {
+            //         log::trace!("setup command executed successfully");
+            //         self.state = InstanceState::Stopped { data };
+            //     }
+            // } else {
+            //     self.state = InstanceState::Stopped { data };
+            // }
+
+            log::info!("Instance installed: {:?}", &this.place);
+        }));
 
         log::info!("Instance started: {:?}", &self.place);
     }
@@ -225,17 +313,17 @@ impl Instance {
     pub fn run(
         at: Arc<Path>,
         desc: model::InstanceDescriptor,
-        data: InstanceData
+        data: InstanceData,
+        rcon_password: &str,
+        console: &console::Console,
+        events: Option<events::Sink>
     ) -> anyhow::Result<InstanceState> {
-        let password = std::env::var("PASSWORD")
-            .expect("no password specified");
-
         utils::patch_server_props(
             at.as_ref(),
             desc.ports.port,
             desc.ports.rcon,
             desc.max_memory as usize,
-            &password
+            rcon_password
         )?;
 
         let mut cmd = Command::new("java");
@@ -255,12 +343,23 @@ impl Instance {
             // .arg(desc.server_jar.as_os_str())
             .arg("--nogui")
             .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
         ;
 
         log::info!("starting process for: {:?}", &at);
 
+        let mut child = cmd.spawn()?;
+
+        if let Some(out) = child.stdout.take() {
+            console::pipe(out, console.clone(), false, events);
+        }
+        if let Some(err) = child.stderr.take() {
+            console::pipe(err, console.clone(), true, None);
+        }
+
         return Ok(InstanceState::Starting {
-            child: cmd.spawn()?,
+            child,
             data
         });
     }
@@ -314,7 +413,14 @@ impl Instance {
 impl Handler<messages::Tick> for Instance {
     type Result = ();
 
-    fn handle(&mut self, _: messages::Tick, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, _: messages::Tick, ctx: &mut Self::Context) -> Self::Result {
+
+        // ticks left till next disk scan
+        if self.ticks == 0 {
+            self.scan_disk(ctx);
+            self.ticks = disk::SCAN_EVERY_TICKS;
+        }
+        self.ticks -= 1;
 
         let (child, data) = match &mut self.state {
             InstanceState::Starting { child, data } | 
@@ -329,6 +435,13 @@ impl Handler<messages::Tick> for Instance {
             
         };
 
+        // an exited child stays in procfs until reaped
+        if let Some(status) = exited(child) {
+            log::error!("server {:?} exited with {}", &self.place, status);
+            self.crash();
+            return;
+        }
+
         if let Ok(process) = procfs::process::Process::new(child.id().try_into().unwrap()) {
             let Ok(status) = process.status() else {
                 return;
@@ -343,19 +456,34 @@ impl Handler<messages::Tick> for Instance {
         }
         log::error!("cannot get process info for {:?}", &self.place);
 
+        self.crash();
+    }
+}
+
+/// reaps the child if it is gone, errors count as gone too
+fn exited(child: &mut Child) -> Option<String> {
+    match child.try_wait() {
+        Ok(Some(status)) => Some(status.to_string()),
+        Ok(None) => None,
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+impl Instance {
+    /// process is gone on its own, rcon and everything of the run go with it
+    fn crash(&mut self) {
         match std::mem::replace(&mut self.state, InstanceState::Swap) {
             InstanceState::Starting { data, .. } |
             InstanceState::Running { data, ..  } => {
                 self.state = InstanceState::Crashed { data };
             },
-            _ => unreachable!()
+            os => self.state = os,
         }
-
     }
 }
 
 impl Handler<instance_messages::Kill> for Instance {
-    type Result = anyhow::Result<()>;
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
 
     fn handle(&mut self, _msg: instance_messages::Kill, _ctx: &mut Self::Context) -> Self::Result {
         let (mut child,data) = match std::mem::replace(&mut self.state, InstanceState::Swap) {
@@ -364,14 +492,22 @@ impl Handler<instance_messages::Kill> for Instance {
             old => {
                 self.state = old;
                 log::info!("server {:?} is already stopped", &self.place);
-                return Ok(())
+                return Box::pin(fut::ready(Ok(())))
             }
         };
         let _ = child.kill();
-        utils::dispose(child);
 
-        self.state = InstanceState::Crashed { data };
-        Ok(())
+        // reap before this returns, so that files are not in use, the instance stays busy meanwhile
+        let reap = tokio::task::spawn_blocking(move || child.wait());
+
+        Box::pin(reap.into_actor(self).map(move |res, this, _| {
+            if let Err(e) = res.map_err(anyhow::Error::from).and_then(|r| r.map_err(anyhow::Error::from)) {
+                log::error!("error while waiting for child to die: {}", e);
+            }
+
+            this.state = InstanceState::Crashed { data };
+            Ok(())
+        }))
     }
 }
 
@@ -389,25 +525,323 @@ impl<O,F> Handler<instance_messages::Instance<O,F>> for Instance
 
 
 impl Handler<rcon::RconMessage> for Instance {
-    type Result = anyhow::Result<()>;
+    type Result = ResponseFuture<anyhow::Result<String>>;
 
     fn handle(&mut self, msg: rcon::RconMessage, _ctx: &mut Self::Context) -> Self::Result {
-        match &mut self.state {
-            InstanceState::Running { rcon, .. } => {
-                Ok(rcon.send(msg.cmd)?)
+        let place = Arc::clone(&self.place);
+        let mut record = model::CommandRecord {
+            at: history::now(),
+            origin: msg.origin,
+            caller: msg.caller.clone(),
+            channel: model::CommandChannel::Rcon,
+            command: msg.cmd.clone(),
+            response: None,
+            error: None
+        };
+
+        let allowed = policy::check(
+            &self.command_rules,
+            &self.env.command_rules,
+            msg.origin,
+            &msg.cmd
+        );
+
+        let response = match (allowed, &mut self.state) {
+            (Err(e), _) => Err(e),
+            (Ok(()), InstanceState::Running { rcon: Some(rcon), .. }) => Ok(rcon.request(msg.cmd)),
+            _ => Err(anyhow!("rcon is not available for {:?}", &place))
+        };
+
+        Box::pin(async move {
+            let res = match response {
+                Ok(response) => tokio::time::timeout(rcon::REQUEST_TIMEOUT, response)
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow!("rcon didn't respond in time"))),
+                Err(e) => Err(e)
+            };
+
+            match &res {
+                Ok(response) => record.response = Some(response.clone()),
+                Err(e) => {
+                    log::error!("rcon command failed: {}", e);
+                    record.error = Some(e.to_string());
+                }
+            }
+            history::record(&place, &record);
+
+            res
+        })
+    }
+}
+
+impl Handler<instance_messages::PlayerAction> for Instance {
+    type Result = ResponseActFuture<Self, anyhow::Result<model::PlayerActionResult>>;
+
+    fn handle(&mut self, msg: instance_messages::PlayerAction, ctx: &mut Self::Context) -> Self::Result {
+        if let Err(e) = players::validate_player_name(&msg.player) {
+            return Box::pin(fut::ready(Err(e)));
+        }
+
+        let cmd = players::command(msg.action, &msg.player, msg.reason.as_deref());
+
+        match std::mem::replace(&mut self.state, InstanceState::Swap) {
+            state @ InstanceState::Running { rcon: Some(_), .. } => {
+                self.state = state;
+                let this = ctx.address();
+                Box::pin(async move {
+                    let message = this.send(rcon::RconMessage { cmd, origin: msg.origin, caller: msg.caller }).await??;
+                    Ok(model::PlayerActionResult {
+                        player: msg.player,
+                        action: msg.action,
+                        via: model::AppliedVia::Rcon,
+                        message
+                    })
+                }.into_actor(self))
+            },
+            // busy till the lists are written, so the server can't be started over them
+            state @ (InstanceState::Crashed { .. } | InstanceState::Stopped { .. }) => {
+                let place = Arc::clone(&self.place);
+                let downloads = self.env.downloads.clone();
+
+                let edit = async move {
+                    let res = tokio::task::spawn_blocking({
+                        let place = Arc::clone(&place);
+                        let player = msg.player.clone();
+                        move || players::apply_offline(&place, msg.action, &player, msg.reason.as_deref(), &downloads)
+                    }).await?;
+
+                    history::record(&place, &model::CommandRecord {
+                        at: history::now(),
+                        origin: msg.origin,
+                        caller: msg.caller,
+                        channel: model::CommandChannel::Rcon,
+                        command: cmd,
+                        response: res.as_ref().ok().cloned(),
+                        error: res.as_ref().err().map(|e| e.to_string())
+                    });
+
+                    Ok(model::PlayerActionResult {
+                        player: msg.player,
+                        action: msg.action,
+                        via: model::AppliedVia::Files,
+                        message: res?
+                    })
+                };
+
+                Box::pin(edit.into_actor(self).map(move |res, this, _| {
+                    this.state = state;
+                    res
+                }))
             },
+            // the server would overwrite edited lists
+            state => {
+                self.state = state;
+                let err = anyhow!("server {:?} is running without rcon or busy, try again later", &self.place);
+                Box::pin(fut::ready(Err(err)))
+            }
+        }
+    }
+}
+
+impl Handler<instance_messages::OnlinePlayers> for Instance {
+    type Result = ResponseFuture<anyhow::Result<model::OnlinePlayers>>;
+
+    fn handle(&mut self, _: instance_messages::OnlinePlayers, _: &mut Self::Context) -> Self::Result {
+        // a poll of the manager, it is neither checked against rules nor kept in history
+        let response = match &self.state {
+            InstanceState::Running { rcon: Some(rcon), .. } => rcon.request("list".to_owned()),
             _ => {
-                log::error!("rcon is not available for {:?}", &self.place);
-                Err(anyhow!("rcon is not available for {:?}", &self.place))
+                let err = anyhow!("rcon is not available for {:?}", &self.place);
+                return Box::pin(async move { Err(err) });
             }
+        };
+
+        Box::pin(async move {
+            let response = tokio::time::timeout(rcon::REQUEST_TIMEOUT, response)
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("rcon didn't respond in time")))?;
+            players::parse_list(&response)
+        })
+    }
+}
+
+impl Handler<instance_messages::PlayerLists> for Instance {
+    type Result = ResponseFuture<anyhow::Result<model::PlayerLists>>;
+
+    fn handle(&mut self, _: instance_messages::PlayerLists, _: &mut Self::Context) -> Self::Result {
+        let place = Arc::clone(&self.place);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || players::lists(&place)).await?
+        })
+    }
+}
+
+impl Handler<instance_messages::ConsoleSubscribe> for Instance {
+    type Result = MessageResult<instance_messages::ConsoleSubscribe>;
+
+    fn handle(&mut self, _: instance_messages::ConsoleSubscribe, _: &mut Self::Context) -> Self::Result {
+        MessageResult(console::stream(&self.console))
+    }
+}
+
+impl Handler<instance_messages::ConsoleInput> for Instance {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: instance_messages::ConsoleInput, _: &mut Self::Context) -> Self::Result {
+        match self.console_writer {
+            Some(writer) if writer != msg.session => {
+                return Err(anyhow!("console is being written to by another session"));
+            },
+            _ => self.console_writer = Some(msg.session)
         }
+
+        let mut record = model::CommandRecord {
+            at: history::now(),
+            origin: model::CommandOrigin::Console,
+            caller: msg.caller.clone(),
+            channel: model::CommandChannel::Stdin,
+            command: msg.line.clone(),
+            response: None,
+            error: None
+        };
+
+        let res = policy::check(
+            &self.command_rules,
+            &self.env.command_rules,
+            model::CommandOrigin::Console,
+            &msg.line
+        ).and_then(|_| match &mut self.state {
+            InstanceState::Starting { child, .. } |
+            InstanceState::Running { child, .. } => {
+                let stdin = child.stdin.as_mut().ok_or(anyhow!("stdin of {:?} is closed", &self.place))?;
+                // one line, one command
+                let line = msg.line.replace(['\r', '\n'], " ");
+                stdin.write_all(format!("{}\n", line).as_bytes())?;
+                stdin.flush()?;
+                Ok(())
+            },
+            _ => Err(anyhow!("server {:?} is not running", &self.place))
+        });
+
+        if let Err(e) = &res {
+            record.error = Some(e.to_string());
+        }
+        history::record(&self.place, &record);
+
+        res
     }
 }
 
-impl Handler<instance_messages::SwitchServer> for Instance {
+impl Handler<instance_messages::ReleaseConsole> for Instance {
+    type Result = ();
+
+    fn handle(&mut self, msg: instance_messages::ReleaseConsole, _: &mut Self::Context) -> Self::Result {
+        if self.console_writer == Some(msg.session) {
+            self.console_writer = None;
+        }
+    }
+}
+
+impl Handler<instance_messages::RotateRconPassword> for Instance {
+    type Result = anyhow::Result<String>;
+
+    fn handle(&mut self, _: instance_messages::RotateRconPassword, _: &mut Self::Context) -> Self::Result {
+        let secret = secret::rotate(&self.place)?;
+
+        // running process keeps the old one, reconnects use `rcon_password`
+        let path = self.place.join(SERVER_PROPERTIES_FILE);
+        let mut props = properties::ServerProperties::load(&path)?;
+        props.set("rcon.password", secret.clone());
+        props.save(&path)?;
+
+        log::info!("rotated rcon password of {:?}", &self.place);
+        Ok(secret)
+    }
+}
+
+impl Handler<instance_messages::PackToken> for Instance {
+    type Result = anyhow::Result<String>;
+
+    fn handle(&mut self, msg: instance_messages::PackToken, _: &mut Self::Context) -> Self::Result {
+        if msg.rotate {
+            log::info!("rotated client pack token of {:?}", &self.place);
+            secret::rotate_pack_token(&self.place)
+        } else {
+            secret::pack_token(&self.place)
+        }
+    }
+}
+
+impl Handler<instance_messages::SetCommandRules> for Instance {
     type Result = anyhow::Result<()>;
 
+    fn handle(&mut self, msg: instance_messages::SetCommandRules, _: &mut Self::Context) -> Self::Result {
+        let compiled = policy::RuleSet::new(&msg.rules)?;
+
+        match &mut self.state {
+            InstanceState::Running { data, .. } |
+            InstanceState::Starting { data, .. } |
+            InstanceState::Crashed { data } |
+            InstanceState::Stopped { data } => {
+                data.desc.command_rules = msg.rules;
+                data.desc.flush(&mut data.manifest)?;
+                self.command_rules = compiled;
+                Ok(())
+            },
+            _ => Err(anyhow!("server {:?} is busy", &self.place))
+        }
+    }
+}
+
+impl Handler<instance_messages::History> for Instance {
+    type Result = ResponseFuture<anyhow::Result<Vec<model::CommandRecord>>>;
+
+    fn handle(&mut self, msg: instance_messages::History, _: &mut Self::Context) -> Self::Result {
+        let place = Arc::clone(&self.place);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || history::query(&place, msg.search.as_deref(), msg.limit)).await?
+        })
+    }
+}
+
+impl Handler<instance_messages::SwitchServer> for Instance {
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
+
     fn handle(&mut self, msg: instance_messages::SwitchServer, ctx: &mut Self::Context) -> Self::Result {
+        let startable = matches!(self.state, InstanceState::Crashed { .. } | InstanceState::Stopped { .. });
+
+        if !(msg.should_run && !msg.force && startable) {
+            return Box::pin(fut::ready(self.switch(msg, ctx)));
+        }
+
+        // state may change while the check runs, `switch` looks at it again
+        Box::pin(self.preflight().into_actor(self).map(move |report, this, ctx| {
+            match report {
+                Ok(report) if report.has_errors() => {
+                    log::error!("preflight of {:?} failed: {}", &this.place, report.summary());
+                    return Err(anyhow!("preflight check failed: {}", report.summary()));
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    log::warn!("cannot run preflight of {:?}: {}", &this.place, e);
+                }
+            }
+
+            this.switch(msg, ctx)
+        }))
+    }
+}
+
+impl Instance {
+    fn switch(&mut self, msg: instance_messages::SwitchServer, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        let startable = matches!(self.state, InstanceState::Crashed { .. } | InstanceState::Stopped { .. });
+
+        let rcon_password = if msg.should_run && startable {
+            secret::rcon_password(&self.place)?
+        } else {
+            String::new()
+        };
+
         match std::mem::replace(&mut self.state, InstanceState::Swap) {
             InstanceState::Running { child, data, .. } => {
                 if msg.should_run {
@@ -425,45 +859,24 @@ impl Handler<instance_messages::SwitchServer> for Instance {
             },
             InstanceState::Crashed { data } | InstanceState::Stopped { data } => {
                 if msg.should_run {
-                    let timeout = self.env.timeout;
-
-                    let rcon = data.desc.ports.rcon;
-
-                    let password = self.env.password.clone();
-
-                    let this = ctx.address();
-
                     log::info!("starting server {:?}", &self.place);
 
+                    let events = self.event_sink(&data.desc);
+
                     let next_state = Self::run(
                         Arc::clone(&self.place),
                         data.desc.clone(),
-                        data
+                        data,
+                        &rcon_password,
+                        &self.console,
+                        events
                     )?;
 
                     self.state = next_state;
+                    self.rcon_password = rcon_password;
 
-                    tokio::spawn(async move {
-
-                        tokio::time::sleep(timeout).await;
-
-                        let rcon = rcon::Rcon::new(
-                            rcon,
-                            password
-                        ).await;
-
-                        match rcon {
-                            Ok(rcon) => {
-                                this.do_send(rcon::RconUp {
-                                    rcon
-                                });
-                            },
-                            Err(e) => {
-                                log::error!("cannot connect to rcon: {}", e);
-                                this.do_send(instance_messages::Kill);
-                            }
-                        }
-                    });
+                    self.rcon_epoch = self.rcon_epoch.wrapping_add(1);
+                    self.connect_rcon(ctx, self.env.timeout, rcon::RETRY_MIN);
                 };
                 Ok(())
             },
@@ -476,43 +889,148 @@ impl Handler<instance_messages::SwitchServer> for Instance {
     }
 }
 
+impl Instance {
+    /// tries once after `delay`, failure comes back as `RconDown` with the next backoff
+    fn connect_rcon(&self, ctx: &mut Context<Self>, delay: std::time::Duration, retry: std::time::Duration) {
+        let Some(desc) = self.desc() else {
+            return;
+        };
+
+        let port = desc.ports.rcon;
+        let password = self.rcon_password.clone();
+        let epoch = self.rcon_epoch;
+        let this = ctx.address();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            match rcon::Rcon::new(port, password).await {
+                Ok(rcon) => this.do_send(rcon::RconUp { rcon, epoch }),
+                Err(e) => {
+                    log::warn!("cannot connect to rcon on {}: {}, retrying in {:?}", port, e, retry);
+                    this.do_send(rcon::RconDown {
+                        epoch,
+                        retry
+                    });
+                }
+            }
+        });
+    }
+}
+
+impl Instance {
+    /// patterns of the profile the server picks, vanilla ones if it picks none or a missing one
+    fn event_sink(&self, desc: &model::InstanceDescriptor) -> Option<events::Sink> {
+        let profile = desc.event_profile.as_deref().unwrap_or(events::DEFAULT_PROFILE);
+
+        let patterns = self.env.event_profiles.get(profile).or_else(|| {
+            log::warn!("no event profile {:?}, {:?} uses {:?}", profile, &self.place, events::DEFAULT_PROFILE);
+            self.env.event_profiles.get(events::DEFAULT_PROFILE)
+        })?;
+
+        match events::Parser::new(patterns) {
+            Ok(parser) => Some(events::Sink::new(parser, Arc::clone(&self.place), self.events.clone())),
+            Err(e) => {
+                log::error!("cannot parse events of {:?}: {}", &self.place, e);
+                None
+            }
+        }
+    }
+}
+
+impl Handler<instance_messages::EventSubscribe> for Instance {
+    type Result = MessageResult<instance_messages::EventSubscribe>;
+
+    fn handle(&mut self, _: instance_messages::EventSubscribe, _: &mut Self::Context) -> Self::Result {
+        MessageResult(events::stream(&self.events))
+    }
+}
+
+impl Handler<instance_messages::EventHistory> for Instance {
+    type Result = ResponseFuture<anyhow::Result<Vec<model::GameEvent>>>;
+
+    fn handle(&mut self, msg: instance_messages::EventHistory, _: &mut Self::Context) -> Self::Result {
+        let place = Arc::clone(&self.place);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || events::query(&place, &msg.kinds, msg.limit)).await?
+        })
+    }
+}
+
 impl Handler<rcon::RconUp> for Instance {
-    type Result = anyhow::Result<()>;
+    type Result = ();
 
-    fn handle(&mut self, msg: rcon::RconUp, _: &mut Self::Context) -> Self::Result {
-        match std::mem::replace(&mut self.state, InstanceState::Swap) {
-            InstanceState::Starting { data, child } => {
-                self.state = InstanceState::Running {
-                    child,
-                    rcon: msg.rcon,
-                    data
-                };
-                Ok(())
-            },
+    fn handle(&mut self, msg: rcon::RconUp, ctx: &mut Self::Context) -> Self::Result {
+        if msg.epoch != self.rcon_epoch {
+            return;
+        }
+
+        let (child, data) = match std::mem::replace(&mut self.state, InstanceState::Swap) {
+            InstanceState::Starting { child, data } |
+            InstanceState::Running { child, data, rcon: None } => (child, data),
             os => {
                 self.state = os;
-                log::error!("rcon is not available for {:?}", &self.place);
-                Err(anyhow!("rcon is not available for {:?}", &self.place))
+                log::warn!("rcon connected to {:?} which is not running", &self.place);
+                return;
             }
-        }
+        };
+
+        use futures::StreamExt;
+
+        // stream just ends when the connection is dropped on purpose
+        let closed = msg.rcon.output_stream()
+            .filter(|out| std::future::ready(matches!(out, rcon::RconOutput::ConnectionClosed)))
+            .boxed()
+            .into_future();
+
+        let epoch = msg.epoch;
+        let this = ctx.address();
+
+        tokio::spawn(async move {
+            if let (Some(_), _) = closed.await {
+                this.do_send(rcon::RconDown { epoch, retry: rcon::RETRY_MIN });
+            }
+        });
+
+        log::info!("rcon of {:?} is up", &self.place);
+
+        self.state = InstanceState::Running {
+            child,
+            rcon: Some(msg.rcon),
+            data
+        };
     }
 }
 
 impl Handler<rcon::RconDown> for Instance {
     type Result = ();
 
-    fn handle(&mut self, _: rcon::RconDown, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: rcon::RconDown, ctx: &mut Self::Context) -> Self::Result {
+        if msg.epoch != self.rcon_epoch {
+            return;
+        }
+
         match std::mem::replace(&mut self.state, InstanceState::Swap) {
-            InstanceState::Running { mut child, data, .. } | InstanceState::Starting { mut child, data } => {
-                let _ = child.kill();
-                utils::dispose(child);
-                self.state = InstanceState::Crashed { data };
+            InstanceState::Starting { mut child, data } |
+            InstanceState::Running { mut child, data, .. } => {
+                // a dead server is the most common reason for rcon to go away
+                if let Some(status) = exited(&mut child) {
+                    log::error!("server {:?} exited with {}, not reconnecting rcon", &self.place, status);
+                    self.state = InstanceState::Crashed { data };
+                    return;
+                }
+
+                log::warn!("rcon of {:?} is unavailable, server keeps running", &self.place);
+                self.state = InstanceState::Running { child, rcon: None, data };
             },
             os => {
+                // stopped meanwhile, nothing to reconnect to
                 self.state = os;
-                log::error!("rcon is not available for {:?}", &self.place);
+                return;
             }
         }
+
+        self.connect_rcon(ctx, msg.retry, (msg.retry * 2).min(rcon::RETRY_MAX));
     }
 }
 
@@ -521,7 +1039,7 @@ impl Handler<rcon::RconSubscription> for Instance {
 
     fn handle(&mut self, _msg: rcon::RconSubscription, _ctx: &mut Self::Context) -> Self::Result {
         match &mut self.state {
-            InstanceState::Running { rcon, .. } => {
+            InstanceState::Running { rcon: Some(rcon), .. } => {
                 Ok(Box::pin(rcon.output_stream()))
             },
             _ => {
@@ -532,6 +1050,14 @@ impl Handler<rcon::RconSubscription> for Instance {
     }
 }
 
+impl Handler<instance_messages::Rename> for Instance {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: instance_messages::Rename, _: &mut Self::Context) -> Self::Result {
+        self.set_name(msg.name)
+    }
+}
+
 impl Handler<instance_messages::AlterServer> for Instance {
     type Result = anyhow::Result<()>;
 
@@ -546,8 +1072,12 @@ impl Handler<instance_messages::AlterServer> for Instance {
             }
         };
 
-        if let Some(port) = msg.port {
-            mfest.desc.ports.port = port;
+        if let Some(ports) = msg.ports {
+            mfest.desc.ports = ports;
+        }
+
+        if let Some(hostnames) = msg.hostnames {
+            mfest.desc.hostnames = hostnames;
         }
 
         if let Some(max_memory) = msg.max_memory {
@@ -558,8 +1088,257 @@ impl Handler<instance_messages::AlterServer> for Instance {
             mfest.desc.java_args = java_args;
         }
 
+        if let Some(quota) = msg.disk_quota {
+            mfest.desc.disk_quota = if quota > 0.0 { Some(quota) } else { None };
+        }
+
+        if let Some(stop_on_quota) = msg.stop_on_quota {
+            mfest.desc.stop_on_quota = stop_on_quota;
+        }
+
+        if let Some(profile) = msg.event_profile {
+            mfest.desc.event_profile = Some(profile).filter(|p| !p.is_empty());
+        }
+
         mfest.desc.flush(&mut mfest.manifest)?;
 
         Ok(())
     }
 }
+
+impl Instance {
+    /// jars are hashed off the actor
+    fn preflight(&self) -> impl std::future::Future<Output = anyhow::Result<model::PreflightReport>> {
+        let mods = self.mods.clone();
+        let place = Arc::clone(&self.place);
+
+        async move {
+            tokio::task::spawn_blocking(move || {
+                let found = mods.scan(&place)?;
+                Ok(preflight::analyze(preflight::detect_loader(&place), &found))
+            }).await?
+        }
+    }
+}
+
+impl Handler<instance_messages::ClientPack> for Instance {
+    type Result = ResponseFuture<anyhow::Result<std::path::PathBuf>>;
+
+    fn handle(&mut self, msg: instance_messages::ClientPack, _: &mut Self::Context) -> Self::Result {
+        let Some(desc) = self.desc() else {
+            let err = anyhow!("server {:?} is not ready", &self.place);
+            return Box::pin(async move { Err(err) });
+        };
+
+        let pack = desc.pack.clone();
+        let name = self.name();
+        let mods = self.mods.clone();
+        let place = Arc::clone(&self.place);
+
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let found = mods.scan(&place)?;
+                packs::client_pack(&place, &name, pack.as_ref(), &found, msg.format)
+            }).await?
+        })
+    }
+}
+
+impl Handler<instance_messages::Preflight> for Instance {
+    type Result = ResponseFuture<anyhow::Result<model::PreflightReport>>;
+
+    fn handle(&mut self, _: instance_messages::Preflight, _: &mut Self::Context) -> Self::Result {
+        if self.desc().is_none() {
+            let err = anyhow!("server {:?} is not ready", &self.place);
+            return Box::pin(async move { Err(err) });
+        }
+
+        Box::pin(self.preflight())
+    }
+}
+
+impl Handler<instance_messages::Mods> for Instance {
+    type Result = ResponseFuture<anyhow::Result<Vec<model::ModInfo>>>;
+
+    fn handle(&mut self, _: instance_messages::Mods, _: &mut Self::Context) -> Self::Result {
+        if self.desc().is_none() {
+            let err = anyhow!("server {:?} is not ready", &self.place);
+            return Box::pin(async move { Err(err) });
+        }
+
+        let mods = self.mods.clone();
+        let place = Arc::clone(&self.place);
+
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || mods.scan(&place)).await?
+        })
+    }
+}
+
+impl Instance {
+    /// checked before anything that leaves more on disk
+    fn ensure_under_quota(&self, what: &str) -> anyhow::Result<()> {
+        if self.disk.as_ref().map(|d| d.over_quota).unwrap_or(false) {
+            return Err(anyhow!("server {:?} is over its disk quota, {}", &self.place, what));
+        }
+        Ok(())
+    }
+
+    /// runs `work` on a stopped server off the actor, busy meanwhile so the server can't be started under it
+    fn while_stopped<T: Send + 'static>(
+        &mut self,
+        work: impl FnOnce(&Path) -> anyhow::Result<T> + Send + 'static,
+    ) -> ResponseActFuture<Self, anyhow::Result<T>> {
+        match std::mem::replace(&mut self.state, InstanceState::Swap) {
+            state @ (InstanceState::Crashed { .. } | InstanceState::Stopped { .. }) => {
+                let place = Arc::clone(&self.place);
+                let work = async move {
+                    tokio::task::spawn_blocking(move || work(&place)).await?
+                };
+
+                Box::pin(work.into_actor(self).map(move |res, this, _| {
+                    this.state = state;
+                    res
+                }))
+            },
+            state => {
+                self.state = state;
+                let err = anyhow!("server {:?} has to be stopped", &self.place);
+                Box::pin(fut::ready(Err(err)))
+            }
+        }
+    }
+}
+
+impl Handler<instance_messages::Worlds> for Instance {
+    type Result = ResponseFuture<anyhow::Result<Vec<model::WorldInfo>>>;
+
+    fn handle(&mut self, _: instance_messages::Worlds, _: &mut Self::Context) -> Self::Result {
+        if self.desc().is_none() {
+            let err = anyhow!("server {:?} is not ready", &self.place);
+            return Box::pin(async move { Err(err) });
+        }
+
+        let place = Arc::clone(&self.place);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || worlds::list(&place)).await?
+        })
+    }
+}
+
+impl Handler<instance_messages::UploadWorld> for Instance {
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
+
+    fn handle(&mut self, mut msg: instance_messages::UploadWorld, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self.ensure_under_quota("no new worlds") {
+            return Box::pin(fut::ready(Err(e)));
+        }
+        self.while_stopped(move |place| {
+            log::info!("uploading world {} to {:?}", &msg.name, place);
+            worlds::upload(place, &msg.name, &mut msg.payload)
+        })
+    }
+}
+
+impl Handler<instance_messages::SwitchWorld> for Instance {
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
+
+    fn handle(&mut self, msg: instance_messages::SwitchWorld, _: &mut Self::Context) -> Self::Result {
+        self.while_stopped(move |place| {
+            log::info!("switching {:?} to world {}", place, &msg.name);
+            worlds::switch(place, &msg.name)
+        })
+    }
+}
+
+impl Handler<instance_messages::DeleteWorld> for Instance {
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
+
+    fn handle(&mut self, msg: instance_messages::DeleteWorld, _: &mut Self::Context) -> Self::Result {
+        self.while_stopped(move |place| {
+            log::info!("deleting world {} of {:?}", &msg.name, place);
+            worlds::delete(place, &msg.name)
+        })
+    }
+}
+
+impl Handler<instance_messages::ResetWorld> for Instance {
+    type Result = ResponseActFuture<Self, anyhow::Result<Option<String>>>;
+
+    fn handle(&mut self, msg: instance_messages::ResetWorld, _: &mut Self::Context) -> Self::Result {
+        // old world stays as a backup
+        if let Err(e) = self.ensure_under_quota("no new backups") {
+            return Box::pin(fut::ready(Err(e)));
+        }
+        self.while_stopped(move |place| {
+            log::info!("resetting world of {:?}", place);
+            worlds::reset(place, msg.seed, msg.level_type)
+        })
+    }
+}
+
+impl Instance {
+    fn scan_disk(&mut self, ctx: &mut Context<Self>) {
+        if self.disk_scan_running || self.desc().is_none() {
+            return;
+        }
+        self.disk_scan_running = true;
+
+        let place = Arc::clone(&self.place);
+        let this = ctx.address();
+
+        std::thread::spawn(move || {
+            this.do_send(instance_messages::DiskScanned {
+                usage: disk::usage(&place)
+            });
+        });
+    }
+}
+
+impl Handler<instance_messages::DiskScanned> for Instance {
+    type Result = ();
+
+    fn handle(&mut self, msg: instance_messages::DiskScanned, ctx: &mut Self::Context) -> Self::Result {
+        self.disk_scan_running = false;
+
+        let mut usage = match msg.usage {
+            Ok(usage) => usage,
+            Err(e) => {
+                log::error!("cannot compute disk usage of {:?}: {}", &self.place, e);
+                return;
+            }
+        };
+
+        let Some(desc) = self.desc() else {
+            return;
+        };
+
+        usage.quota = desc.disk_quota.map(|q| (q * 1024.0 * 1024.0 * 1024.0) as u64);
+
+        if let Some(quota) = usage.quota {
+            usage.near_quota = usage.total as f64 >= quota as f64 * disk::WARN_RATIO;
+            usage.over_quota = usage.total > quota;
+        }
+
+        let host_low = usage.host_free.map(|f| f < self.env.disk_reserve).unwrap_or(false);
+
+        if usage.over_quota {
+            log::warn!("server {:?} uses {} bytes, over its quota of {:?}", &self.place, usage.total, usage.quota);
+        } else if usage.near_quota {
+            log::warn!("server {:?} uses {} bytes, close to its quota of {:?}", &self.place, usage.total, usage.quota);
+        }
+
+        if host_low {
+            log::warn!("host has only {:?} bytes free", usage.host_free);
+        }
+
+        let running = matches!(self.state, InstanceState::Running { .. } | InstanceState::Starting { .. });
+
+        if desc.stop_on_quota && running && (usage.over_quota || host_low) {
+            log::warn!("stopping {:?} to save disk space", &self.place);
+            ctx.notify(instance_messages::SwitchServer { should_run: false, force: false });
+        }
+
+        self.disk = Some(usage);
+    }
+}
@@ -15,6 +15,22 @@ pub mod model;
 pub mod messages;
 pub mod instance;
 pub mod rcon;
+pub mod mods;
+pub mod preflight;
+pub mod packs;
+pub mod properties;
+pub mod worlds;
+pub mod disk;
+pub mod migrations;
+pub mod infer;
+pub mod trash;
+pub mod proxy;
+pub mod history;
+pub mod policy;
+pub mod players;
+pub mod secret;
+pub mod console;
+pub mod events;
 pub mod utils;
 
 #[derive(serde::Deserialize)]
@@ -22,6 +38,13 @@ struct IdParams {
     name: String,
 }
 
+#[derive(serde::Deserialize)]
+struct ClientPackParams {
+    name: String,
+    format: Option<model::ClientPackFormat>,
+    token: String,
+}
+
 #[derive(askama::Template)]
 #[template(path = "page.html")]
 struct Page<'p, C: Display, T: Display, D> 
@@ -38,9 +61,11 @@ struct Page<'p, C: Display, T: Display, D>
 #[route("/graphql", method = "GET", method = "HEAD", method = "POST")]
 async fn graphql_e(
     schema: web::Data<graphql::SrvsSchema>, 
+    http: HttpRequest,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+    let caller = graphql::Caller(http.peer_addr().map(|a| a.ip().to_string()));
+    schema.execute(req.into_inner().data(caller)).await.into()
 }
 
 async fn graphql_ws(
@@ -88,6 +113,18 @@ async fn command(info: web::Query<IdParams>) -> impl Responder {
     }
 }
 
+#[get("/console")]
+async fn console_page(info: web::Query<IdParams>) -> impl Responder {
+    Page {
+        deps: ["shared.js"],
+        chunk: "console.js",
+        title: format!("Console of {}",&info.name),
+        page_props: serde_json::json!({
+            "name": info.name
+        })
+    }
+}
+
 #[get("/alter")]
 async fn alter(info: web::Query<IdParams>) -> impl Responder {
     Page {
@@ -106,17 +143,65 @@ async fn renew(info: web::Query<IdParams>, native: web::Data<Addr<Servers>>) ->
         name: info.name.clone()
     }).await.unwrap_or(None);
 
+    let proposal = native.send(messages::native_messages::ProposeRepair {
+        name: info.name.clone()
+    }).await.unwrap_or(None);
+
     Page {
         deps: ["shared.js","validate.js"],
         chunk: "renew.js",
         title: format!("Renew server {}",&info.name),
         page_props: serde_json::json!({
             "name": info.name,
-            "data": data
+            "data": data,
+            "proposal": proposal
         })
     }
 }
 
+#[get("/client_pack")]
+async fn client_pack(
+    info: web::Query<ClientPackParams>,
+    native: web::Data<Addr<Servers>>,
+) -> actix_web::Result<actix_files::NamedFile> {
+    let format = info.format.unwrap_or(model::ClientPackFormat::Mrpack);
+
+    let Some(addr) = native.send(messages::native_messages::AddrOf::new(info.name.clone()))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)? else {
+        return Err(actix_web::error::ErrorNotFound("no such server"));
+    };
+
+    // own token, so the link can be handed to players without the admin password
+    let token = addr.send(messages::instance_messages::PackToken { rotate: false })
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if info.token != token {
+        log::error!("wrong token for client pack of {}", info.name);
+        return Err(actix_web::error::ErrorUnauthorized("wrong token"));
+    }
+
+    let path = addr.send(messages::instance_messages::ClientPack { format })
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(|e| {
+            log::error!("cannot build client pack of {}: {}", info.name, e);
+            actix_web::error::ErrorInternalServerError(e)
+        })?;
+
+    let file = actix_files::NamedFile::open(path)?
+        .set_content_disposition(actix_web::http::header::ContentDisposition {
+            disposition: actix_web::http::header::DispositionType::Attachment,
+            parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                format!("{}.{}", info.name, format.extension())
+            )],
+        });
+
+    Ok(file)
+}
+
 #[derive(Debug,Clone,Copy)]
 enum Mode {
     Prod,
@@ -165,12 +250,20 @@ async fn main() -> std::io::Result<()> {
                 .guard(guard::Header("upgrade", "websocket"))
                 .to(graphql_ws),
         )
+        .service(
+            web::resource("/console_ws")
+                .guard(guard::Get())
+                .guard(guard::Header("upgrade", "websocket"))
+                .to(console::console_ws),
+        )
         .service(graphql_e)
         .service(index)
         .service(create)
         .service(alter)
         .service(command)
+        .service(console_page)
         .service(renew)
+        .service(client_pack)
     };
 
     simple_logger::SimpleLogger::new().env().init().unwrap();
@@ -230,7 +323,52 @@ async fn main() -> std::io::Result<()> {
     let static_srv_dir = std::env::var("STATIC_DIR")
         .unwrap_or("./static".to_owned());
 
-    let native = native::Servers::new(srvrs_dir,rcons,ports,timeout,password.clone()).start();
+    let pack_mirror = std::env::var("PACK_MIRROR")
+        .ok()
+        .map(PathBuf::from);
+
+    let pack_offline = std::env::var("PACK_OFFLINE")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    let downloads = packs::DownloadSource::new(
+        pack_mirror,
+        pack_offline,
+        std::env::var("CURSEFORGE_API_KEY").ok()
+    ).expect("cannot initialize download source");
+
+    // in GB, servers opted in are stopped when host has less free space than this
+    let disk_reserve = std::env::var("DISK_RESERVE")
+        .ok()
+        .map(|r| r.parse::<f64>().expect("bad DISK_RESERVE format"))
+        .map(|r| (r * 1024.0 * 1024.0 * 1024.0) as u64)
+        .unwrap_or(0);
+
+    // in days, deleted servers are purged after this
+    let trash_retention = std::env::var("TRASH_RETENTION")
+        .ok()
+        .map(|r| r.parse::<f64>().expect("bad TRASH_RETENTION format"))
+        .map(|r| Duration::from_secs_f64(r * 24.0 * 60.0 * 60.0))
+        .unwrap_or(Duration::from_secs(7 * 24 * 60 * 60));
+
+    // json array of rules every rcon command is checked against, after those of its server
+    let command_rules = std::env::var("COMMAND_RULES")
+        .map(|path| policy::load_rules(path.as_ref()).expect("bad COMMAND_RULES file"))
+        .unwrap_or_else(|_| policy::RuleSet::new(&policy::default_rules()).expect("default rules compile"));
+
+    // json object of profile name to log patterns, added to the built in `vanilla` one
+    let event_profiles = std::env::var("EVENT_PATTERNS")
+        .map(|path| events::load_profiles(path.as_ref()).expect("bad EVENT_PATTERNS file"))
+        .unwrap_or_else(|_| events::default_profiles());
+
+    let native = native::Servers::new(srvrs_dir,rcons,ports,native::ServersConfig {
+        timeout,
+        downloads,
+        disk_reserve,
+        trash_retention,
+        command_rules,
+        event_profiles,
+    }).start();
 
     let native_timer = native.clone();
     
@@ -242,6 +380,25 @@ async fn main() -> std::io::Result<()> {
         }
     });
 
+    // minecraft proxy routing by hostname, off unless a port is given
+    if let Ok(proxy_port) = std::env::var("PROXY_PORT") {
+        let config = proxy::ProxyConfig {
+            addr,
+            port: proxy_port.parse().expect("bad PROXY_PORT format"),
+            unknown_motd: std::env::var("PROXY_MOTD")
+                .unwrap_or("There is no server at this address".to_owned()),
+        };
+
+        let servers = native.clone();
+        actix::spawn(async move {
+            if let Err(e) = proxy::run(config, servers).await {
+                log::error!("minecraft proxy stopped: {}", e);
+            }
+        });
+    }
+
+    let admin_password = password.clone();
+
     let schema = Arc::new(graphql::schema(native.clone(),password));
 
     log::info!("starting HTTP server on port {port} in {mode:?} mode");
@@ -254,6 +411,7 @@ async fn main() -> std::io::Result<()> {
             let app = App::new()
                 .app_data(Data::from(schema.clone()))
                 .app_data(Data::new(data_native.clone()))
+                .app_data(Data::new(console::AdminPassword(admin_password.clone())))
                 .service(
                     web::scope("/static")
                         .wrap({
@@ -46,7 +46,9 @@ pub mod native_messages {
         pub url: url::Url,
         // pub instance_upload: UploadValue,
         pub max_memory: f64,
-        pub ports: model::Ports,
+        pub ports: model::PortsRequest,
+        /// one of the loaded profiles, `vanilla` if left out
+        pub event_profile: Option<String>,
 
         pub ext: P
     }
@@ -76,6 +78,8 @@ pub mod native_messages {
     #[rtype(result = "anyhow::Result<()>")]
     pub struct AlterServer {
         pub name: String,
+        pub ports: model::PortsChange,
+        /// its `ports` are filled in once the change is reserved
         pub msg: super::instance_messages::AlterServer
     }
 
@@ -93,11 +97,53 @@ pub mod native_messages {
     #[rtype(result = "Vec<String>")]
     pub struct ListBroken;
 
+    /// game port of the server claiming the hostname
+    #[derive(Message,Debug)]
+    #[rtype(result = "Option<u16>")]
+    pub struct Route {
+        pub host: String
+    }
+
     #[derive(Message,Debug)]
     #[rtype(result = "Option<serde_json::Value>")]
     pub struct DataOfBroken {
         pub name: String
     }
+
+    /// descriptor a broken server would get, ports checked against what is taken
+    #[derive(Message,Debug)]
+    #[rtype(result = "Option<model::RepairProposal>")]
+    pub struct ProposeRepair {
+        pub name: String
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct RenameServer {
+        pub old: String,
+        pub new: String
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "Vec<model::TrashEntry>")]
+    pub struct ListTrash;
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct RestoreDeleted {
+        pub id: String
+    }
+
+    /// everything when no id is given
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct PurgeTrash {
+        pub id: Option<String>
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "Vec<model::QuarantinedServer>")]
+    pub struct ListQuarantined;
 }
 
 /// instance actor messages
@@ -107,21 +153,183 @@ pub mod instance_messages {
     #[derive(Message,Debug)]
     #[rtype(result = "anyhow::Result<()>")]
     pub struct SwitchServer {
-        pub should_run: bool
+        pub should_run: bool,
+        /// start even if preflight check reports errors
+        pub force: bool
     }
 
     #[derive(Message,Debug)]
     #[rtype(result = "anyhow::Result<()>")]
     pub struct Kill;
 
+    /// change the human readable name, directory stays
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct Rename {
+        pub name: String
+    }
+
     #[derive(Message,Debug)]
     #[rtype(result = "anyhow::Result<()>")]
     pub struct AlterServer {
         pub max_memory: Option<f64>,
-        pub port: Option<u16>,
+        pub ports: Option<model::Ports>,
+        /// replaces all of them
+        pub hostnames: Option<Vec<String>>,
         pub java_args: Option<Vec<String>>,
+        /// 0 removes the quota
+        pub disk_quota: Option<f64>,
+        pub stop_on_quota: Option<bool>,
+        /// empty goes back to the default
+        pub event_profile: Option<String>,
+    }
+
+    /// result of background walk over the server directory
+    #[derive(Message,Debug)]
+    #[rtype(result = "()")]
+    pub struct DiskScanned {
+        pub usage: anyhow::Result<model::DiskUsage>
     }
 
+    /// events parsed from the output from now on
+    #[derive(Message)]
+    #[rtype(result = "crate::events::EventStream")]
+    pub struct EventSubscribe;
+
+    /// stored events, newest first
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<Vec<model::GameEvent>>")]
+    pub struct EventHistory {
+        /// all of them if empty
+        pub kinds: Vec<model::GameEventKind>,
+        pub limit: usize
+    }
+
+    /// process output from now on
+    #[derive(Message)]
+    #[rtype(result = "crate::console::ConsoleStream")]
+    pub struct ConsoleSubscribe;
+
+    /// line for the process input, takes the writer lock if it is free
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct ConsoleInput {
+        pub session: u64,
+        pub line: String,
+        pub caller: Option<String>
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "()")]
+    pub struct ReleaseConsole {
+        pub session: u64
+    }
+
+    /// new secret is used from the next start, returns it
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<String>")]
+    pub struct RotateRconPassword;
+
+    /// token for downloading the client pack, a new one when `rotate` is set
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<String>")]
+    pub struct PackToken {
+        pub rotate: bool
+    }
+
+    /// replaces rules of the server
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct SetCommandRules {
+        pub rules: Vec<model::CommandRule>
+    }
+
+    /// over rcon when running, editing the lists when stopped
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<model::PlayerActionResult>")]
+    pub struct PlayerAction {
+        pub player: String,
+        pub action: model::PlayerAction,
+        pub reason: Option<String>,
+        pub origin: model::CommandOrigin,
+        pub caller: Option<String>
+    }
+
+    /// parsed `list` of a running server
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<model::OnlinePlayers>")]
+    pub struct OnlinePlayers;
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<model::PlayerLists>")]
+    pub struct PlayerLists;
+
+    /// commands sent to the server, newest first
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<Vec<model::CommandRecord>>")]
+    pub struct History {
+        pub search: Option<String>,
+        pub limit: usize
+    }
+
+    /// parsed metadata of jars in `mods` and `plugins`
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<Vec<model::ModInfo>>")]
+    pub struct Mods;
+
+    /// path to zip with mods and configs players need, built if mod set has changed
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<std::path::PathBuf>")]
+    pub struct ClientPack {
+        pub format: model::ClientPackFormat
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<Vec<model::WorldInfo>>")]
+    pub struct Worlds;
+
+    #[derive(Message)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct UploadWorld {
+        pub name: String,
+        pub payload: async_graphql::UploadValue
+    }
+
+    impl std::fmt::Debug for UploadWorld {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f
+                .debug_struct("UploadWorld")
+                .field("name", &self.name)
+                .field("payload", &"UploadValue")
+                .finish()
+        }
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct SwitchWorld {
+        pub name: String
+    }
+
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<()>")]
+    pub struct DeleteWorld {
+        pub name: String
+    }
+
+    /// active world is kept as backup, returns its name
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<Option<String>>")]
+    pub struct ResetWorld {
+        pub seed: Option<String>,
+        pub level_type: Option<String>
+    }
+
+    /// dependency and conflict analysis of the mods
+    #[derive(Message,Debug)]
+    #[rtype(result = "anyhow::Result<model::PreflightReport>")]
+    pub struct Preflight;
+
     #[derive(Message,Debug)]
     #[rtype(result = "Option<O>")]
     pub struct Instance<O,F>
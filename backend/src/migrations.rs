@@ -0,0 +1,43 @@
+use serde_json::{Map, Value};
+
+/// version of `msrvDesc.json` this build writes
+pub const SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>) -> anyhow::Result<()>;
+
+/// `MIGRATIONS[n]` upgrades manifest of version `n` to `n + 1`
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+    v0_to_v1,
+];
+
+/// manifests written before versioning, `java_args` and `memory` might have been omitted by hand
+fn v0_to_v1(m: &mut Map<String, Value>) -> anyhow::Result<()> {
+    m.entry("java_args").or_insert_with(|| Value::Array(vec![]));
+    m.entry("memory").or_insert(Value::Null);
+    Ok(())
+}
+
+pub fn version_of(v: &Value) -> u32 {
+    v.get("schema_version")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// brings manifest up to `SCHEMA_VERSION`, returns version it had
+/// manifests from newer builds are left as is
+pub fn migrate(v: &mut Value) -> anyhow::Result<u32> {
+    let from = version_of(v);
+
+    let Some(m) = v.as_object_mut() else {
+        anyhow::bail!("manifest is not an object");
+    };
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        log::info!("migrating manifest from version {} to {}", version, version + 1);
+        migration(m)?;
+        m.insert("schema_version".to_owned(), Value::from(version as u32 + 1));
+    }
+
+    Ok(from)
+}
@@ -1,10 +1,14 @@
-use std::{fs::File, io::{Seek, SeekFrom}};
+use std::{collections::BTreeMap, fs::File, io::Write, path::{Path, PathBuf}};
 
 use async_graphql::SimpleObject;
 use serde::{Deserialize,Serialize};
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct InstanceDescriptor {
+    /// missing in manifests from before versioning
+    #[serde(default)]
+    pub schema_version: u32,
+
     pub name: String,
 
     pub mods: url::Url,
@@ -17,6 +21,46 @@ pub struct InstanceDescriptor {
     pub max_memory: f64,
 
     pub ports: Ports,
+
+    /// modpack the server was created from
+    #[serde(default)]
+    pub pack: Option<PackInfo>,
+
+    // in GB
+    #[serde(default)]
+    pub disk_quota: Option<f64>,
+    /// stop the server once it goes over quota or host runs out of space
+    #[serde(default)]
+    pub stop_on_quota: bool,
+
+    /// addresses the proxy routes to this server
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+
+    /// checked before the global ones on every rcon command
+    #[serde(default)]
+    pub command_rules: Vec<CommandRule>,
+
+    /// patterns its log is parsed with, `vanilla` if left out
+    #[serde(default)]
+    pub event_profile: Option<String>,
+
+    /// fields we don't know of, kept so that manifests of newer versions survive a rewrite
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum PackSource {
+    Modrinth,
+    CurseForge
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct PackInfo {
+    pub source: PackSource,
+    pub name: String,
+    pub version: Option<String>
 }
 
 #[derive(Debug)]
@@ -25,33 +69,382 @@ pub enum IDError {
     IO(serde_json::Error)
 }
 
+/// `msrvDesc.json` of an instance, rewritten atomically with previous version kept as `.bak`
+#[derive(Debug)]
+pub struct Manifest {
+    dir: PathBuf,
+    /// what we know is on disk, to skip rewriting the same thing
+    written: Option<String>,
+}
+
+impl Manifest {
+    pub fn at(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+            written: None,
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(crate::instance::MANIFEST_NAME)
+    }
+
+    fn with_suffix(&self, suffix: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", crate::instance::MANIFEST_NAME, suffix))
+    }
+
+    pub fn backup_path(&self) -> PathBuf {
+        self.with_suffix("bak")
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path().is_file() || self.backup_path().is_file()
+    }
+
+    /// write to temp, fsync, keep previous as `.bak`, rename over
+    pub fn write(&mut self, json: String) -> anyhow::Result<()> {
+        self.replace(json, true)
+    }
+
+    /// like `write` but `.bak` is left alone, for when it is the only good copy
+    pub fn write_without_backup(&mut self, json: String) -> anyhow::Result<()> {
+        self.replace(json, false)
+    }
+
+    fn replace(&mut self, json: String, backup: bool) -> anyhow::Result<()> {
+        if self.written.as_deref() == Some(json.as_str()) {
+            return Ok(());
+        }
+
+        let path = self.path();
+        let tmp = self.with_suffix("tmp");
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+
+        if backup && path.is_file() {
+            let backup = self.backup_path();
+            let _ = std::fs::remove_file(&backup);
+            if std::fs::hard_link(&path, &backup).is_err() {
+                std::fs::copy(&path, &backup)?;
+            }
+        }
+
+        std::fs::rename(&tmp, &path)?;
+        File::open(&self.dir)?.sync_all()?;
+
+        self.written = Some(json);
+        Ok(())
+    }
+}
+
 impl InstanceDescriptor {
-    pub fn flush(&self,file: &mut File) -> anyhow::Result<()> {
-        file.seek(SeekFrom::Start(0))?;
-        file.set_len(0)?;
-        Ok(serde_json::to_writer(file, self)?)
+    pub fn flush(&self, manifest: &mut Manifest) -> anyhow::Result<()> {
+        manifest.write(serde_json::to_string(self)?)
     }
 
-    pub fn from_file(file: &mut File) -> Result<Self,IDError> {
-        if let Ok(id) = serde_json::from_reader::<_,Self>(&mut *file) {
-            return Ok(id)
+    /// main file is broken, so it must not become the backup
+    fn restore(&self, manifest: &mut Manifest) -> anyhow::Result<()> {
+        manifest.write_without_backup(serde_json::to_string(self)?)
+    }
+
+    /// reads manifest falling back to its `.bak` if it is empty or broken
+    /// old manifests are migrated in place, copy of the original is kept as `.v<N>.bak`
+    pub fn from_file(manifest: &mut Manifest) -> Result<Self,IDError> {
+        let main = Self::from_path(manifest, &manifest.path());
+
+        let Err(main_err) = main else {
+            return main;
         };
-        let val: serde_json::Value = serde_json::from_reader(file)
-            .map_err(|e| IDError::IO(e))?;
-        Err(IDError::JSON(val))
+
+        match Self::from_path(manifest, &manifest.backup_path()) {
+            Ok(id) => {
+                log::warn!("manifest at {:?} is broken, recovered from backup", manifest.path());
+                // forget what was on disk so the good copy gets written over
+                manifest.written = None;
+                id.restore(manifest).map_err(|e| IDError::IO(serde_json::Error::io(std::io::Error::other(e))))?;
+                Ok(id)
+            },
+            Err(_) => Err(main_err)
+        }
+    }
+
+    fn from_path(manifest: &mut Manifest, path: &Path) -> Result<Self,IDError> {
+        let raw = std::fs::read_to_string(path).map_err(|e| IDError::IO(serde_json::Error::io(e)))?;
+
+        let mut val: serde_json::Value = serde_json::from_str(&raw)
+            .map_err(IDError::IO)?;
+
+        let from = match crate::migrations::migrate(&mut val) {
+            Ok(from) => from,
+            Err(e) => {
+                log::error!("cannot migrate manifest: {}", e);
+                return Err(IDError::JSON(val));
+            }
+        };
+
+        let id = match serde_json::from_value::<Self>(val.clone()) {
+            Ok(id) => id,
+            Err(e) => {
+                log::error!("bad manifest: {}", e);
+                return Err(IDError::JSON(val));
+            }
+        };
+
+        if from < crate::migrations::SCHEMA_VERSION {
+            let backup = manifest.with_suffix(&format!("v{}.bak", from));
+            std::fs::write(&backup, &raw).map_err(|e| IDError::IO(serde_json::Error::io(e)))?;
+            let res = if path == manifest.path() {
+                id.flush(manifest)
+            } else {
+                id.restore(manifest)
+            };
+            res.map_err(|e| IDError::IO(serde_json::Error::io(std::io::Error::other(e))))?;
+            log::info!("manifest upgraded from version {}, original kept at {:?}", from, &backup);
+        } else if path == manifest.path() {
+            manifest.written = Some(raw);
+        }
+
+        Ok(id)
     }
 }
 
-#[derive(Clone,Copy, Deserialize, Serialize, Debug, async_graphql::InputObject)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Ports {
     pub port: u16,
-    pub rcon: u16
+    pub rcon: u16,
+    /// ports mods need, like query or voice chat, taken from the game port range
+    #[serde(default)]
+    pub extra: BTreeMap<String, u16>
+}
+
+impl Ports {
+    /// everything taken from the game port range
+    pub fn game_ports(&self) -> impl Iterator<Item = u16> + '_ {
+        std::iter::once(self.port).chain(self.extra.values().copied())
+    }
+}
+
+/// directory found in the data folder without a manifest, moved aside on startup
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct QuarantinedServer {
+    pub name: String,
+    /// guessed from `server.properties`
+    pub port: Option<u16>,
+    pub rcon: Option<u16>,
+    /// guessed from jvm argument files and start scripts, in GB
+    pub max_memory: Option<f64>,
+    pub java_args: Vec<String>
+}
+
+/// descriptor for a broken server put together from its old manifest and its files
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct RepairProposal {
+    pub url: Option<url::Url>,
+    pub java_args: Vec<String>,
+    // in GB
+    pub max_memory: Option<f64>,
+    pub port: Option<u16>,
+    pub rcon: Option<u16>,
+    /// why it can't be applied as is, empty when it can
+    pub problems: Vec<String>
+}
+
+/// who issued a command, told by the endpoint it came through
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum CommandOrigin {
+    /// console websocket of the web ui
+    Console,
+    /// graphql api, rcon page of the web ui included
+    Api,
+    /// the manager on its own
+    Manager
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum RuleAction {
+    Allow,
+    Deny
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum PatternKind {
+    /// `*` and `?`, matched against the whole command ignoring case
+    Glob,
+    Regex
+}
+
+/// decides whether a command may be sent over rcon
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "CommandRuleInput")]
+pub struct CommandRule {
+    pub action: RuleAction,
+    pub kind: PatternKind,
+    pub pattern: String,
+    /// whom the rule applies to, everyone if empty
+    #[serde(default)]
+    #[graphql(default)]
+    pub origins: Vec<CommandOrigin>
+}
+
+/// how a command got to the server
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum CommandChannel {
+    #[default]
+    Rcon,
+    /// written to the process input from the console
+    Stdin
+}
+
+/// command sent to a server as kept in its history
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct CommandRecord {
+    /// unix seconds
+    pub at: u64,
+    pub origin: CommandOrigin,
+    /// address the command came from, none for the manager itself
+    #[serde(default)]
+    pub caller: Option<String>,
+    #[serde(default)]
+    pub channel: CommandChannel,
+    pub command: String,
+    pub response: Option<String>,
+    /// set when the command didn't go through
+    pub error: Option<String>
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum PlayerAction {
+    Kick,
+    Ban,
+    Pardon,
+    WhitelistAdd,
+    WhitelistRemove,
+    Op,
+    Deop
+}
+
+/// how a player action reached the server
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum AppliedVia {
+    Rcon,
+    /// server was stopped, its json lists were edited
+    Files
+}
+
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct PlayerActionResult {
+    pub player: String,
+    pub action: PlayerAction,
+    pub via: AppliedVia,
+    /// response of the server, or what was done to the files
+    pub message: String
+}
+
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct OnlinePlayers {
+    pub online: u32,
+    pub max: u32,
+    pub players: Vec<String>
+}
+
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct PlayerEntry {
+    pub name: String,
+    pub uuid: String,
+    /// only bans have it
+    pub reason: Option<String>
+}
+
+/// `whitelist.json`, `ops.json` and `banned-players.json`
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct PlayerLists {
+    pub whitelist: Vec<PlayerEntry>,
+    pub ops: Vec<PlayerEntry>,
+    pub banned: Vec<PlayerEntry>
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum GameEventKind {
+    Join,
+    Leave,
+    Chat,
+    Death,
+    Advancement,
+    /// `Can't keep up!` warnings
+    Lag
+}
+
+/// regex over a log line without its prefix, `player` and `message` groups go into the event
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct EventPattern {
+    pub kind: GameEventKind,
+    pub pattern: String
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct GameEvent {
+    /// unix seconds
+    pub at: u64,
+    pub kind: GameEventKind,
+    pub player: Option<String>,
+    pub message: Option<String>,
+    /// whole line the event was parsed from
+    pub line: String
+}
+
+/// deleted server kept around until its retention passes
+#[derive(Clone, Serialize, Debug, SimpleObject)]
+pub struct TrashEntry {
+    /// directory name inside the trash, used to restore or purge
+    pub id: String,
+    pub name: String,
+    /// unix seconds
+    pub deleted_at: u64,
+    pub expires_at: u64
+}
+
+/// ports left out are allocated by the manager
+#[derive(Clone, Default, Debug, async_graphql::InputObject)]
+pub struct PortsRequest {
+    pub port: Option<u16>,
+    pub rcon: Option<u16>,
+    #[graphql(default)]
+    pub extra: Vec<ExtraPortRequest>
+}
+
+#[derive(Clone, Debug, async_graphql::InputObject)]
+pub struct ExtraPortRequest {
+    pub name: String,
+    /// allocated when left out
+    pub port: Option<u16>
+}
+
+/// changes to ports of an existing server
+#[derive(Clone, Default, Debug)]
+pub struct PortsChange {
+    pub port: Option<u16>,
+    pub rcon: Option<u16>,
+    /// added, or moved when the name is already there
+    pub extra: Vec<ExtraPortRequest>,
+    pub remove_extra: Vec<String>
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct NamedPort {
+    pub server: String,
+    pub name: String,
+    pub port: u16
 }
 
 #[derive(SimpleObject)]
 pub struct PortsInfo {
     pub ports: Vec<u16>,
     pub rcons: Vec<u16>,
+    /// named extra ports, they are among `ports` too
+    pub extra: Vec<NamedPort>,
     pub port_limits: [u16;2],
     pub rcon_limits: [u16;2]
 }
@@ -60,6 +453,8 @@ pub struct PortsInfo {
 pub enum InstanceState {
     // can be acted upon
     Running,
+    /// process is up, rcon is being reconnected
+    RconUnavailable,
     Stopped,
     Crashed,
 
@@ -67,4 +462,171 @@ pub enum InstanceState {
     Starting,
     Downloading,
     Busy
-}
\ No newline at end of file
+}
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, Hash, async_graphql::Enum)]
+pub enum ModLoader {
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+    Bukkit,
+    // jar without any metadata we know of
+    Unknown
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum ModSide {
+    Both,
+    Client,
+    Server
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum DependencyKind {
+    Required,
+    Optional,
+    Incompatible
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct ModDependency {
+    pub mod_id: String,
+    /// as declared by the mod, maven range for forge-likes, semver predicate for fabric-likes
+    pub version_range: Option<String>,
+    pub kind: DependencyKind,
+    pub side: ModSide
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct ModInfo {
+    /// file name of the jar, relative to the instance
+    pub file: String,
+    /// sha1 of the jar
+    pub hash: String,
+
+    pub mod_id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub authors: Vec<String>,
+    pub loader: ModLoader,
+    pub side: ModSide,
+    pub dependencies: Vec<ModDependency>,
+    /// ids declared as provided, including mods bundled as nested jars
+    pub provides: Vec<String>
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, async_graphql::Enum)]
+pub enum IssueSeverity {
+    // blocks start
+    Error,
+    Warning
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum IssueKind {
+    MissingDependency,
+    VersionMismatch,
+    DuplicateMod,
+    LoaderMismatch,
+    Incompatible,
+    ClientOnly
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct PreflightIssue {
+    pub severity: IssueSeverity,
+    pub kind: IssueKind,
+    pub mod_id: String,
+    pub file: String,
+    pub message: String
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct PreflightReport {
+    /// loader the server is assumed to run
+    pub loader: Option<ModLoader>,
+    pub issues: Vec<PreflightIssue>
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientPackFormat {
+    /// modrinth pack, jars are shipped as overrides
+    Mrpack,
+    /// plain `mods/` and `config/`
+    Zip
+}
+
+impl ClientPackFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ClientPackFormat::Mrpack => "mrpack",
+            ClientPackFormat::Zip => "zip",
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct WorldInfo {
+    pub name: String,
+    /// the one `level-name` points at
+    pub active: bool
+}
+
+/// in bytes
+#[derive(Clone, Default, Deserialize, Serialize, Debug, SimpleObject)]
+pub struct DiskUsage {
+    pub total: u64,
+    pub worlds: u64,
+    pub mods: u64,
+    pub logs: u64,
+    pub backups: u64,
+    pub other: u64,
+
+    pub quota: Option<u64>,
+    pub near_quota: bool,
+    pub over_quota: bool,
+    /// free space left on the host
+    pub host_free: Option<u64>,
+    /// unix seconds
+    pub computed_at: u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_json(name: &str) -> String {
+        serde_json::json!({
+            "schema_version": crate::migrations::SCHEMA_VERSION,
+            "name": name,
+            "mods": "https://example.com/mods",
+            "java_args": [],
+            "memory": null,
+            "max_memory": 2.0,
+            "ports": { "port": 25565, "rcon": 25575 }
+        }).to_string()
+    }
+
+    #[test]
+    fn recovery_keeps_the_backup() {
+        let dir = std::env::temp_dir().join(format!("msrv-manifest-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+
+        let mut manifest = Manifest::at(&dir);
+        std::fs::write(manifest.path(), "{ broken").unwrap();
+        std::fs::write(manifest.backup_path(), manifest_json("good")).unwrap();
+
+        let id = InstanceDescriptor::from_file(&mut manifest).unwrap();
+        assert_eq!(id.name, "good");
+
+        let backup: InstanceDescriptor = serde_json::from_str(&std::fs::read_to_string(manifest.backup_path()).unwrap()).unwrap();
+        assert_eq!(backup.name, "good");
+
+        // and the main file is good again
+        let main: InstanceDescriptor = serde_json::from_str(&std::fs::read_to_string(manifest.path()).unwrap()).unwrap();
+        assert_eq!(main.name, "good");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,524 @@
+use std::{collections::HashMap, fs::File, io::{Cursor, Read, Seek}, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::SystemTime};
+
+use anyhow::anyhow;
+use sha1::{Digest, Sha1};
+use zip::ZipArchive;
+
+use crate::model::{DependencyKind, ModDependency, ModInfo, ModLoader, ModSide};
+
+/// directories of an instance which are scanned for jars
+pub const MOD_DIRS: [&str; 2] = ["mods", "plugins"];
+
+#[derive(Debug)]
+struct CachedJar {
+    modified: Option<SystemTime>,
+    size: u64,
+    mods: Vec<ModInfo>,
+}
+
+/// parsed metadata of jars, keyed by path and checked against mtime and size, shared with blocking tasks
+#[derive(Debug, Default, Clone)]
+pub struct ModCache(Arc<Mutex<HashMap<PathBuf, CachedJar>>>);
+
+impl ModCache {
+    /// this blocks thread, only changed jars are hashed and parsed
+    pub fn scan(&self, at: impl AsRef<Path>) -> anyhow::Result<Vec<ModInfo>> {
+        let mut cache = self.0.lock().map_err(|_| anyhow!("mod cache is poisoned"))?;
+        let mut seen = HashMap::new();
+        let mut out = Vec::new();
+
+        for (file, path) in list_jars(at.as_ref())? {
+            let meta = std::fs::metadata(&path)?;
+            let modified = meta.modified().ok();
+            let size = meta.len();
+
+            let jar = match cache.remove(&path) {
+                Some(jar) if jar.modified == modified && jar.size == size => jar,
+                _ => {
+                    log::info!("parsing mod metadata of {:?}", &path);
+                    let hash = hash_file(&path)?;
+                    let mods = parse_jar(&path, &file, &hash).unwrap_or_else(|e| {
+                        log::error!("cannot parse {:?}: {}", &path, e);
+                        vec![unknown(&file, &hash)]
+                    });
+                    CachedJar { modified, size, mods }
+                }
+            };
+
+            out.extend(jar.mods.iter().cloned());
+            seen.insert(path, jar);
+        }
+
+        // drop jars that are gone
+        *cache = seen;
+
+        Ok(out)
+    }
+}
+
+/// relative file names and full pathes of jars of an instance, sorted by name
+pub fn list_jars(at: &Path) -> anyhow::Result<Vec<(String, std::path::PathBuf)>> {
+    let mut jars = Vec::new();
+
+    for dir in MOD_DIRS {
+        let Ok(entries) = std::fs::read_dir(at.join(dir)) else {
+            continue;
+        };
+
+        for e in entries.filter_map(|e| e.ok()) {
+            let path = e.path();
+            if path.is_file() && path.extension().map(|e| e == "jar").unwrap_or(false) {
+                let file = format!("{}/{}", dir, e.file_name().to_string_lossy());
+                jars.push((file, path));
+            }
+        }
+    }
+
+    jars.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(jars)
+}
+
+pub fn hash_file(path: impl AsRef<Path>) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn unknown(file: &str, hash: &str) -> ModInfo {
+    let stem = file
+        .rsplit('/')
+        .next()
+        .unwrap_or(file)
+        .trim_end_matches(".jar")
+        .to_owned();
+
+    ModInfo {
+        file: file.to_owned(),
+        hash: hash.to_owned(),
+        mod_id: stem.clone(),
+        name: stem,
+        version: None,
+        authors: vec![],
+        loader: ModLoader::Unknown,
+        side: ModSide::Both,
+        dependencies: vec![],
+        provides: vec![],
+    }
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut buf = Vec::new();
+    entry.read_to_end(&mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// this blocks thread
+pub fn parse_jar(path: impl AsRef<Path>, file: &str, hash: &str) -> anyhow::Result<Vec<ModInfo>> {
+    let archive = ZipArchive::new(File::open(path)?)?;
+    parse_archive(archive, file, hash, 0)
+}
+
+/// how deep we follow jars bundled inside of jars
+const MAX_NESTING: usize = 2;
+
+fn parse_archive<R: Read + Seek>(mut archive: ZipArchive<R>, file: &str, hash: &str, depth: usize) -> anyhow::Result<Vec<ModInfo>> {
+    let mods = if let Some(toml) = read_entry(&mut archive, "META-INF/neoforge.mods.toml") {
+        let manifest = read_entry(&mut archive, "META-INF/MANIFEST.MF");
+        parse_mods_toml(&toml, manifest.as_deref(), true)?
+    } else if let Some(toml) = read_entry(&mut archive, "META-INF/mods.toml") {
+        let manifest = read_entry(&mut archive, "META-INF/MANIFEST.MF");
+        parse_mods_toml(&toml, manifest.as_deref(), false)?
+    } else if let Some(json) = read_entry(&mut archive, "quilt.mod.json") {
+        vec![parse_quilt_json(&json)?]
+    } else if let Some(json) = read_entry(&mut archive, "fabric.mod.json") {
+        vec![parse_fabric_json(&json)?]
+    } else if let Some(yml) = read_entry(&mut archive, "plugin.yml") {
+        vec![parse_plugin_yml(&yml)?]
+    } else {
+        return Ok(vec![unknown(file, hash)]);
+    };
+
+    let nested = if depth < MAX_NESTING {
+        nested_ids(&mut archive, depth)
+    } else {
+        vec![]
+    };
+
+    Ok(mods
+        .into_iter()
+        .map(|mut m| {
+            m.file = file.to_owned();
+            m.hash = hash.to_owned();
+            m.provides.extend(nested.iter().cloned());
+            m
+        })
+        .collect())
+}
+
+/// ids of mods shipped as jar-in-jar (fabric `META-INF/jars`, forge `META-INF/jarjar`)
+fn nested_ids<R: Read + Seek>(archive: &mut ZipArchive<R>, depth: usize) -> Vec<String> {
+    let names: Vec<String> = archive
+        .file_names()
+        .filter(|n| n.starts_with("META-INF/jars/") || n.starts_with("META-INF/jarjar/"))
+        .filter(|n| n.ends_with(".jar"))
+        .map(|n| n.to_owned())
+        .collect();
+
+    let mut ids = Vec::new();
+
+    for name in names {
+        let mut buf = Vec::new();
+        let Ok(mut entry) = archive.by_name(&name) else {
+            continue;
+        };
+        if entry.read_to_end(&mut buf).is_err() {
+            continue;
+        }
+        drop(entry);
+
+        let Ok(inner) = ZipArchive::new(Cursor::new(buf)) else {
+            continue;
+        };
+
+        if let Ok(mods) = parse_archive(inner, &name, "", depth + 1) {
+            for m in mods.into_iter().filter(|m| m.loader != ModLoader::Unknown) {
+                ids.push(m.mod_id);
+                ids.extend(m.provides);
+            }
+        }
+    }
+
+    ids
+}
+
+fn blank(loader: ModLoader) -> ModInfo {
+    ModInfo {
+        file: String::new(),
+        hash: String::new(),
+        mod_id: String::new(),
+        name: String::new(),
+        version: None,
+        authors: vec![],
+        loader,
+        side: ModSide::Both,
+        dependencies: vec![],
+        provides: vec![],
+    }
+}
+
+fn parse_side(s: &str) -> ModSide {
+    match s.to_ascii_lowercase().as_str() {
+        "client" => ModSide::Client,
+        "server" | "dedicated_server" => ModSide::Server,
+        _ => ModSide::Both,
+    }
+}
+
+/// `Implementation-Version` from jar's MANIFEST.MF, used to resolve `${file.jarVersion}`
+fn jar_version(manifest: Option<&str>) -> Option<String> {
+    manifest?
+        .lines()
+        .find_map(|l| l.strip_prefix("Implementation-Version:"))
+        .map(|v| v.trim().to_owned())
+}
+
+fn parse_mods_toml(src: &str, manifest: Option<&str>, neoforge: bool) -> anyhow::Result<Vec<ModInfo>> {
+    let root: toml::Table = src.parse()?;
+
+    let mods = root
+        .get("mods")
+        .and_then(|m| m.as_array())
+        .ok_or_else(|| anyhow!("mods.toml has no [[mods]]"))?;
+
+    let client_only = root.get("clientSideOnly").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let deps = root.get("dependencies").and_then(|d| d.as_table());
+
+    let mut out = Vec::with_capacity(mods.len());
+
+    for m in mods {
+        let Some(mod_id) = m.get("modId").and_then(|v| v.as_str()) else {
+            continue;
+        };
+
+        let mut info = blank(ModLoader::Forge);
+        info.mod_id = mod_id.to_owned();
+        info.name = m
+            .get("displayName")
+            .and_then(|v| v.as_str())
+            .unwrap_or(mod_id)
+            .to_owned();
+
+        info.version = match m.get("version").and_then(|v| v.as_str()) {
+            Some("${file.jarVersion}") => jar_version(manifest),
+            Some(v) => Some(v.to_owned()),
+            None => None,
+        };
+
+        info.authors = match m.get("authors").or_else(|| root.get("authors")) {
+            Some(toml::Value::String(s)) => s
+                .split(',')
+                .map(|a| a.trim().to_owned())
+                .filter(|a| !a.is_empty())
+                .collect(),
+            Some(toml::Value::Array(a)) => a
+                .iter()
+                .filter_map(|a| a.as_str())
+                .map(|a| a.to_owned())
+                .collect(),
+            _ => vec![],
+        };
+
+        // displayTest tells which side doesn't need to have the mod, IGNORE_ALL_VERSION only makes it optional on both
+        info.side = match m.get("displayTest").and_then(|v| v.as_str()) {
+            _ if client_only => ModSide::Client,
+            Some("IGNORE_SERVER_VERSION") => ModSide::Server,
+            _ => ModSide::Both,
+        };
+
+        info.dependencies = deps
+            .and_then(|d| d.get(mod_id))
+            .and_then(|d| d.as_array())
+            .map(|ds| ds.iter().filter_map(parse_toml_dependency).collect())
+            .unwrap_or_default();
+
+        let is_neo = neoforge || info.dependencies.iter().any(|d| d.mod_id == "neoforge");
+        if is_neo {
+            info.loader = ModLoader::NeoForge;
+        }
+
+        out.push(info);
+    }
+
+    Ok(out)
+}
+
+fn parse_toml_dependency(d: &toml::Value) -> Option<ModDependency> {
+    let mod_id = d.get("modId")?.as_str()?.to_owned();
+
+    // forge uses `mandatory`, neoforge and newer forge use `type`
+    let kind = match d.get("type").and_then(|v| v.as_str()) {
+        Some(t) => match t.to_ascii_lowercase().as_str() {
+            "required" => DependencyKind::Required,
+            "incompatible" => DependencyKind::Incompatible,
+            _ => DependencyKind::Optional,
+        },
+        None => match d.get("mandatory").and_then(|v| v.as_bool()) {
+            Some(false) => DependencyKind::Optional,
+            _ => DependencyKind::Required,
+        },
+    };
+
+    Some(ModDependency {
+        mod_id,
+        version_range: d.get("versionRange").and_then(|v| v.as_str()).map(|v| v.to_owned()),
+        kind,
+        side: d.get("side").and_then(|v| v.as_str()).map(parse_side).unwrap_or(ModSide::Both),
+    })
+}
+
+fn json_authors(v: Option<&serde_json::Value>) -> Vec<String> {
+    let Some(serde_json::Value::Array(a)) = v else {
+        return vec![];
+    };
+
+    a.iter()
+        .filter_map(|a| match a {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Object(o) => o.get("name").and_then(|n| n.as_str()).map(|n| n.to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// fabric allows either a single predicate or a list of alternatives
+fn json_range(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(a) => {
+            let alts: Vec<_> = a.iter().filter_map(|s| s.as_str()).collect();
+            if alts.is_empty() {
+                None
+            } else {
+                Some(alts.join(" || "))
+            }
+        },
+        _ => None,
+    }
+}
+
+fn parse_fabric_json(src: &str) -> anyhow::Result<ModInfo> {
+    let root: serde_json::Value = serde_json::from_str(src)?;
+
+    let mod_id = root
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("fabric.mod.json has no id"))?;
+
+    let mut info = blank(ModLoader::Fabric);
+    info.mod_id = mod_id.to_owned();
+    info.name = root.get("name").and_then(|v| v.as_str()).unwrap_or(mod_id).to_owned();
+    info.version = root.get("version").and_then(|v| v.as_str()).map(|v| v.to_owned());
+    info.authors = json_authors(root.get("authors"));
+    info.side = root
+        .get("environment")
+        .and_then(|v| v.as_str())
+        .map(parse_side)
+        .unwrap_or(ModSide::Both);
+    info.provides = root
+        .get("provides")
+        .and_then(|p| p.as_array())
+        .map(|p| p.iter().filter_map(|p| p.as_str()).map(|p| p.to_owned()).collect())
+        .unwrap_or_default();
+
+    for (key, kind) in [
+        ("depends", DependencyKind::Required),
+        ("recommends", DependencyKind::Optional),
+        ("suggests", DependencyKind::Optional),
+        ("breaks", DependencyKind::Incompatible),
+    ] {
+        let Some(deps) = root.get(key).and_then(|d| d.as_object()) else {
+            continue;
+        };
+
+        info.dependencies.extend(deps.iter().map(|(id, range)| ModDependency {
+            mod_id: id.clone(),
+            version_range: json_range(range),
+            kind,
+            side: ModSide::Both,
+        }));
+    }
+
+    Ok(info)
+}
+
+fn parse_quilt_json(src: &str) -> anyhow::Result<ModInfo> {
+    let root: serde_json::Value = serde_json::from_str(src)?;
+
+    let loader = root
+        .get("quilt_loader")
+        .ok_or_else(|| anyhow!("quilt.mod.json has no quilt_loader"))?;
+
+    let mod_id = loader
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("quilt.mod.json has no id"))?;
+
+    let meta = loader.get("metadata");
+
+    let mut info = blank(ModLoader::Quilt);
+    info.mod_id = mod_id.to_owned();
+    info.name = meta
+        .and_then(|m| m.get("name"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(mod_id)
+        .to_owned();
+    info.version = loader.get("version").and_then(|v| v.as_str()).map(|v| v.to_owned());
+    info.authors = meta
+        .and_then(|m| m.get("contributors"))
+        .and_then(|c| c.as_object())
+        .map(|c| c.keys().cloned().collect())
+        .unwrap_or_default();
+    info.side = root
+        .get("minecraft")
+        .and_then(|m| m.get("environment"))
+        .and_then(|v| v.as_str())
+        .map(parse_side)
+        .unwrap_or(ModSide::Both);
+    info.provides = loader
+        .get("provides")
+        .and_then(|p| p.as_array())
+        .map(|p| {
+            p.iter()
+                .filter_map(|p| match p {
+                    serde_json::Value::String(id) => Some(id.clone()),
+                    serde_json::Value::Object(o) => o.get("id").and_then(|v| v.as_str()).map(|v| v.to_owned()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for (key, default_kind) in [("depends", DependencyKind::Required), ("breaks", DependencyKind::Incompatible)] {
+        let Some(deps) = loader.get(key).and_then(|d| d.as_array()) else {
+            continue;
+        };
+
+        info.dependencies.extend(deps.iter().filter_map(|d| match d {
+            serde_json::Value::String(id) => Some(ModDependency {
+                mod_id: id.clone(),
+                version_range: None,
+                kind: default_kind,
+                side: ModSide::Both,
+            }),
+            serde_json::Value::Object(o) => {
+                let optional = o.get("optional").and_then(|v| v.as_bool()).unwrap_or(false);
+                Some(ModDependency {
+                    mod_id: o.get("id")?.as_str()?.to_owned(),
+                    version_range: o.get("versions").and_then(json_range),
+                    kind: if optional && default_kind == DependencyKind::Required {
+                        DependencyKind::Optional
+                    } else {
+                        default_kind
+                    },
+                    side: ModSide::Both,
+                })
+            },
+            _ => None,
+        }));
+    }
+
+    Ok(info)
+}
+
+fn parse_plugin_yml(src: &str) -> anyhow::Result<ModInfo> {
+    let root: serde_yaml::Value = serde_yaml::from_str(src)?;
+
+    let name = root
+        .get("name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("plugin.yml has no name"))?;
+
+    let mut info = blank(ModLoader::Bukkit);
+    info.mod_id = name.to_owned();
+    info.name = name.to_owned();
+    info.side = ModSide::Server;
+    info.version = match root.get("version") {
+        Some(serde_yaml::Value::String(s)) => Some(s.clone()),
+        Some(serde_yaml::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+
+    info.authors = root
+        .get("author")
+        .and_then(|a| a.as_str())
+        .map(|a| vec![a.to_owned()])
+        .unwrap_or_default();
+
+    if let Some(authors) = root.get("authors").and_then(|a| a.as_sequence()) {
+        info.authors.extend(authors.iter().filter_map(|a| a.as_str()).map(|a| a.to_owned()));
+    }
+
+    info.provides = root
+        .get("provides")
+        .and_then(|p| p.as_sequence())
+        .map(|p| p.iter().filter_map(|p| p.as_str()).map(|p| p.to_owned()).collect())
+        .unwrap_or_default();
+
+    for (key, kind) in [("depend", DependencyKind::Required), ("softdepend", DependencyKind::Optional)] {
+        let Some(deps) = root.get(key).and_then(|d| d.as_sequence()) else {
+            continue;
+        };
+
+        info.dependencies.extend(deps.iter().filter_map(|d| d.as_str()).map(|id| ModDependency {
+            mod_id: id.to_owned(),
+            version_range: None,
+            kind,
+            side: ModSide::Server,
+        }));
+    }
+
+    Ok(info)
+}
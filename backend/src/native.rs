@@ -15,17 +15,42 @@ use utils::Indices;
 
 use actix::prelude::*;
 
+/// where directories without a manifest are moved to, inside the data folder
+pub const QUARANTINE_DIR: &str = ".quarantine";
+
 
 #[derive(Clone,Debug)]
 pub struct Server {
     addr: Addr<instance::Instance>,
     ports: model::Ports,
+    /// addresses the proxy routes to this server
+    hostnames: Vec<String>,
 }
 
 #[derive(Clone,Debug)]
 pub struct BrokenServer {
     at: Arc<Path>,
-    had: Option<serde_json::Value>
+    had: Option<serde_json::Value>,
+    name: String
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PortKind {
+    Game,
+    Rcon
+}
+
+/// settings of the whole manager, what instances need of them is passed on in `InstanceEnv`
+pub struct ServersConfig {
+    pub timeout: Duration,
+    pub downloads: packs::DownloadSource,
+    /// in bytes
+    pub disk_reserve: u64,
+    pub trash_retention: Duration,
+    /// rcon rules applied to every server, after its own
+    pub command_rules: policy::RuleSet,
+    /// log patterns by profile name, servers pick one
+    pub event_profiles: HashMap<String, Vec<model::EventPattern>>,
 }
 
 pub struct Servers {
@@ -33,13 +58,28 @@ pub struct Servers {
     rcon_range: Indices,
     port_range: Indices,
     timeout: Duration,
-    password: String,
-
+    downloads: packs::DownloadSource,
+    disk_reserve: u64,
+    trash_retention: Duration,
+    /// rcon rules of the whole manager, handed to every instance
+    command_rules: Arc<policy::RuleSet>,
+    /// log patterns by profile name, servers pick one
+    event_profiles: Arc<HashMap<String, Vec<model::EventPattern>>>,
+
+    /// keyed by directory, which is named by the id
     servers: HashMap<std::sync::Arc<Path>, Server>,
+    /// names of both loaded and broken servers
+    names: HashMap<String, uuid::Uuid>,
 
     broken: Vec<BrokenServer>,
+
+    ticks: u64,
+    purge_running: bool,
 }
 
+/// expired trash is looked for once in this many ticks
+const PURGE_EVERY_TICKS: u64 = 200;
+
 impl Actor for Servers {
     type Context = actix::Context<Self>;
 
@@ -49,35 +89,68 @@ impl Actor for Servers {
             ctx.stop();
             return;
         };
+        trash::clean_leftovers(&self.trash_dir());
         servers.filter_map(|de| {
             let de = de.ok()?;
+            // our own service directories, like quarantine
+            if de.file_name().to_string_lossy().starts_with('.') {
+                return None;
+            }
             if de.path().is_dir() {
                 Some(de)
             } else {
                 None
             }
         }).for_each(|at| {
-            let arc_path: Arc<Path> = at.path().into();
-            let env = instance::InstanceEnv {
-                timeout: self.timeout,
-                servers: ctx.address(),
-                password: self.password.clone(),
+            let path = at.path();
+            let dir_name = at.file_name().to_string_lossy().into_owned();
+
+            if !model::Manifest::at(&path).exists() {
+                log::error!("no manifest at {:?} - quarantining", &path);
+                if let Err(e) = self.quarantine(&path) {
+                    log::error!("couldn't quarantine {:?}: {}", &path, e);
+                }
+                return;
+            }
+
+            // directories from before ids were named after their server
+            let (id, legacy_name) = match uuid::Uuid::parse_str(&dir_name) {
+                Ok(id) => (id, None),
+                Err(_) => {
+                    let id = uuid::Uuid::new_v4();
+                    if let Err(e) = std::fs::rename(&path, self.id_to_path(id)) {
+                        log::error!("couldn't move {:?} to its id {}: {}", &path, id, e);
+                        return;
+                    }
+                    log::info!("moved {:?} to its id {}", &path, id);
+                    (id, Some(dir_name))
+                }
             };
-            match instance::Instance::load(Arc::clone(&arc_path),env) {
-                Ok((instance,ports)) => {
+
+            let arc_path: Arc<Path> = self.id_to_path(id).into();
+
+            match instance::Instance::load(Arc::clone(&arc_path),self.env(ctx)) {
+                Ok((mut instance,ports)) => {
+                    let name = self.unique_name(&legacy_name.unwrap_or_else(|| instance.name()), id);
+                    if name != instance.name() {
+                        if let Err(e) = instance.set_name(name.clone()) {
+                            log::error!("couldn't rename server at {:?}: {}", &arc_path, e);
+                        }
+                    }
+
                     if self.take_ports(&ports) {
-                        self.servers.insert(arc_path, Server {
-                            addr: instance.start(),
-                            ports
-                        });
+                        self.names.insert(name, id);
+                        self.add_instance(arc_path, instance, ports);
                     }
                 },
                 Err(e) => {
                     match e {
                         instance::LoadError::PathIsNotDir => {},
                         instance::LoadError::NoManifest(e) => {
-                            log::error!("couldn't load server at {:?} due to: {:?} - nuking", &arc_path, e);
-                            let _ = std::fs::remove_dir_all(arc_path.as_ref());
+                            log::error!("couldn't load server at {:?} due to: {:?} - quarantining", &arc_path, e);
+                            if let Err(e) = self.quarantine(&arc_path) {
+                                log::error!("couldn't quarantine {:?}: {}", &arc_path, e);
+                            }
                         },
                         instance::LoadError::BadManifest(ide) => {
                             log::error!("couldn't load server at {:?} due to bad manifest - broken", &arc_path);
@@ -87,7 +160,13 @@ impl Actor for Servers {
                                 model::IDError::JSON(e) => Some(e),
                             };
 
-                            self.broken.push(BrokenServer { at: arc_path, had });
+                            let name = legacy_name
+                                .or_else(|| had.as_ref()?.get("name")?.as_str().map(|n| n.to_owned()))
+                                .unwrap_or_else(|| id.to_string());
+                            let name = self.unique_name(&name, id);
+
+                            self.names.insert(name.clone(), id);
+                            self.broken.push(BrokenServer { at: arc_path, had, name });
                         },
                     };
                 }
@@ -98,7 +177,7 @@ impl Actor for Servers {
     fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
         log::info!("stopping servers");
         let stop_futures = self.servers.values().map(|srv| {
-            srv.addr.send(instance_messages::SwitchServer {should_run: false})
+            srv.addr.send(instance_messages::SwitchServer {should_run: false, force: false})
         }).collect::<FuturesUnordered<_>>();
 
         let stop = stop_futures.collect::<Vec<_>>().into_actor(self).then(|res, _, _| {
@@ -124,18 +203,40 @@ impl Handler<native_messages::Stop> for Servers {
 }
 
 impl Servers {
-    fn name_to_path<P: AsRef<Path>>(&self, name: P) -> PathBuf {
-        self.servers_dir.as_path().join(name)
+    fn id_to_path(&self, id: uuid::Uuid) -> PathBuf {
+        self.servers_dir.as_path().join(id.to_string())
+    }
+
+    /// directory of a loaded or broken server
+    fn resolve(&self, name: &str) -> Option<PathBuf> {
+        self.names.get(name).map(|id| self.id_to_path(*id))
+    }
+
+    /// valid and not taken, suffixed with the id otherwise
+    fn unique_name(&self, name: &str, id: uuid::Uuid) -> String {
+        let name = utils::sanitize_server_name(name);
+        match self.names.get(&name) {
+            Some(other) if *other != id => format!("{}-{}", name, &id.to_string()[..8]),
+            _ => name,
+        }
+    }
+
+    fn check_free_name(&self, name: &str) -> anyhow::Result<()> {
+        utils::validate_server_name(name)?;
+        if self.names.contains_key(name) {
+            return Err(anyhow!("server name is already in use"));
+        }
+        Ok(())
     }
 
     pub fn new<P: AsRef<Path>>(
         path: P,
         rcon_range: Range<u16>,
         port_range: Range<u16>,
-        timeout: Duration,
-        password: String,
+        config: ServersConfig,
     ) -> Self {
         let servers_dir = path.as_ref().to_owned();
+        let ServersConfig { timeout, downloads, disk_reserve, trash_retention, command_rules, event_profiles } = config;
 
         let rcon_range = Indices::new(
             rcon_range.clone()
@@ -147,13 +248,57 @@ impl Servers {
             rcon_range,
             port_range,
             servers: HashMap::new(),
+            names: HashMap::new(),
             timeout,
-            password,
+            downloads,
+            disk_reserve,
+            trash_retention,
+            command_rules: Arc::new(command_rules),
+            event_profiles: Arc::new(event_profiles),
             broken: Vec::new(),
+            ticks: 0,
+            purge_running: false,
         };
         
     }
 
+    fn trash_dir(&self) -> PathBuf {
+        self.servers_dir.join(trash::TRASH_DIR)
+    }
+
+    fn env(&self, ctx: &Context<Self>) -> instance::InstanceEnv {
+        instance::InstanceEnv {
+            timeout: self.timeout,
+            servers: ctx.address(),
+            downloads: self.downloads.clone(),
+            disk_reserve: self.disk_reserve,
+            command_rules: Arc::clone(&self.command_rules),
+            event_profiles: Arc::clone(&self.event_profiles),
+        }
+    }
+
+    fn quarantine_dir(&self) -> PathBuf {
+        self.servers_dir.join(QUARANTINE_DIR)
+    }
+
+    /// moves directory aside, suffixed with time if the name is already quarantined
+    fn quarantine(&self, at: &Path) -> anyhow::Result<PathBuf> {
+        let dir = self.quarantine_dir();
+        std::fs::create_dir_all(&dir)?;
+
+        let name = at.file_name().ok_or(anyhow!("path has no name"))?.to_string_lossy().into_owned();
+
+        let mut target = dir.join(&name);
+        if target.exists() {
+            let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+            target = dir.join(format!("{}-{}", name, ts));
+        }
+
+        std::fs::rename(at, &target)?;
+        log::info!("moved {:?} to {:?}", at, &target);
+        Ok(target)
+    }
+
     fn hb(&mut self) {
         for (_, i) in &mut self.servers {
             i.addr.do_send(messages::Tick);
@@ -163,32 +308,162 @@ impl Servers {
     fn nuke(&mut self, who: impl AsRef<Path>) -> anyhow::Result<()> {
         let path = who.as_ref();
         if let Some(server) = self.servers.remove(path.into()) {
-            self.port_range.free(server.ports.port)?;
-            self.rcon_range.free(server.ports.rcon)?;
+            self.free_ports(&server.ports);
         }
         std::fs::remove_dir_all(path)?;
         Ok(())
     }
 
     fn take_ports(&mut self, ports: &model::Ports) -> bool {
-        if let Err(e) = self.port_range.try_take(ports.port) {
-            log::error!(" port {} is taken", e);
-            return false;
+        let mut taken = Vec::new();
+
+        for port in ports.game_ports() {
+            if let Err(e) = self.port_range.try_take(port) {
+                log::error!(" port {} is taken", e);
+                self.release(&taken);
+                return false;
+            }
+            taken.push((PortKind::Game, port));
         }
 
         if let Err(e) = self.rcon_range.try_take(ports.rcon) {
             log::error!(" rcon port {} is taken", e);
-            let _ = self.port_range.free(ports.port);
+            self.release(&taken);
             return false;
         }
 
         return  true
     }
 
+    fn range_of(&mut self, kind: PortKind) -> &mut Indices {
+        match kind {
+            PortKind::Game => &mut self.port_range,
+            PortKind::Rcon => &mut self.rcon_range,
+        }
+    }
+
+    /// explicit port is probed on the host, missing one allocated
+    fn take_port(&mut self, kind: PortKind, want: Option<u16>, taken: &mut Vec<(PortKind, u16)>) -> anyhow::Result<u16> {
+        let range = self.range_of(kind);
+
+        let port = match want {
+            Some(port) => range.take_available(port).map(|_| port)?,
+            None => range.allocate().ok_or(anyhow!("no free {:?} ports left", kind))?,
+        };
+
+        taken.push((kind, port));
+        Ok(port)
+    }
+
+    fn release(&mut self, taken: &[(PortKind, u16)]) {
+        for (kind, port) in taken {
+            let _ = self.range_of(*kind).free(*port);
+        }
+    }
+
+    fn reserve_ports(&mut self, req: model::PortsRequest) -> anyhow::Result<model::Ports> {
+        let mut taken = Vec::new();
+
+        let res = self.reserve_into(req, &mut taken);
+        if res.is_err() {
+            self.release(&taken);
+        }
+        res
+    }
+
+    fn reserve_into(&mut self, req: model::PortsRequest, taken: &mut Vec<(PortKind, u16)>) -> anyhow::Result<model::Ports> {
+        let mut ports = model::Ports {
+            port: self.take_port(PortKind::Game, req.port, taken)?,
+            rcon: self.take_port(PortKind::Rcon, req.rcon, taken)?,
+            extra: Default::default(),
+        };
+
+        for e in req.extra {
+            utils::validate_port_name(&e.name)?;
+            if ports.extra.contains_key(&e.name) {
+                return Err(anyhow!("port {} is given twice", &e.name));
+            }
+            let port = self.take_port(PortKind::Game, e.port, taken)?;
+            ports.extra.insert(e.name, port);
+        }
+
+        Ok(ports)
+    }
+
+    /// new ports are taken right away, old ones are up to the caller to free
+    fn change_into(&mut self, old: &model::Ports, change: model::PortsChange, taken: &mut Vec<(PortKind, u16)>) -> anyhow::Result<model::Ports> {
+        let mut new = old.clone();
+
+        if let Some(port) = change.port.filter(|p| *p != old.port) {
+            new.port = self.take_port(PortKind::Game, Some(port), taken)?;
+        }
+
+        if let Some(rcon) = change.rcon.filter(|p| *p != old.rcon) {
+            new.rcon = self.take_port(PortKind::Rcon, Some(rcon), taken)?;
+        }
+
+        for name in change.remove_extra {
+            new.extra.remove(&name);
+        }
+
+        for e in change.extra {
+            utils::validate_port_name(&e.name)?;
+            if e.port.is_some() && old.extra.get(&e.name) == e.port.as_ref() {
+                continue;
+            }
+            let port = self.take_port(PortKind::Game, e.port, taken)?;
+            new.extra.insert(e.name, port);
+        }
+
+        Ok(new)
+    }
+
+    /// empty is the same as none, anything else has to be a loaded profile
+    fn check_event_profile(&self, profile: Option<&str>) -> anyhow::Result<()> {
+        match profile {
+            Some(p) if !p.is_empty() && !self.event_profiles.contains_key(p) => {
+                let mut known: Vec<_> = self.event_profiles.keys().map(|k| k.as_str()).collect();
+                known.sort();
+                Err(anyhow!("no event profile {:?}, known are: {}", p, known.join(", ")))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// normalizes them, none may be claimed by another server
+    fn check_hostnames(&self, owner: &Path, hostnames: &mut Vec<String>) -> anyhow::Result<()> {
+        for h in hostnames.iter_mut() {
+            *h = h.trim().trim_end_matches('.').to_ascii_lowercase();
+            utils::validate_hostname(h)?;
+        }
+        hostnames.sort();
+        hostnames.dedup();
+
+        let claimed = self.servers.iter()
+            .filter(|(at, _)| at.as_ref() != owner)
+            .flat_map(|(_, srv)| srv.hostnames.iter())
+            .find(|h| hostnames.contains(h));
+
+        if let Some(h) = claimed {
+            return Err(anyhow!("hostname {} is already used by another server", h));
+        }
+        Ok(())
+    }
+
+    fn free_ports(&mut self, ports: &model::Ports) {
+        for port in ports.game_ports() {
+            let _ = self.port_range.free(port);
+        }
+        let _ = self.rcon_range.free(ports.rcon);
+    }
+
     fn add_instance(&mut self, path: Arc<Path>, instance: instance::Instance, ports: model::Ports) {
+        let hostnames = instance.desc().map(|d| d.hostnames.clone()).unwrap_or_default();
+
         self.servers.insert(path, Server {
             addr: instance.start(),
-            ports
+            ports,
+            hostnames
         });
     }
     
@@ -200,7 +475,7 @@ impl Handler<native_messages::ListBroken> for Servers {
     type Result = Vec<String>;
 
     fn handle(&mut self, _: native_messages::ListBroken, _: &mut Self::Context) -> Self::Result {
-        self.broken.iter().filter_map(|b| b.at.file_name()).map(|i| i.to_string_lossy().into_owned()).collect()
+        self.broken.iter().map(|b| b.name.clone()).collect()
     }
 }
 
@@ -212,7 +487,9 @@ impl Handler<native_messages::InitServer<ReNewServer>> for Servers {
     fn handle(&mut self, msg: native_messages::InitServer<ReNewServer>, ctx: &mut Self::Context) -> Self::Result {
         let name = msg.ext.0.as_str();
 
-        let target = self.name_to_path(name);
+        let Some(target) = self.resolve(name) else {
+            return Err(anyhow!("server not found"));
+        };
 
         let Some(bs) = self.broken.iter().find(|b| b.at.as_ref() == &*target) else {
             return Err(anyhow!("server not found"));
@@ -220,25 +497,35 @@ impl Handler<native_messages::InitServer<ReNewServer>> for Servers {
 
         let at = Arc::clone(&bs.at);
 
+        self.check_event_profile(msg.event_profile.as_deref())?;
+
+        // broken servers hold no ports
+        let ports = self.reserve_ports(msg.ports)?;
+
         let desc: model::InstanceDescriptor = model::InstanceDescriptor {
+            schema_version: migrations::SCHEMA_VERSION,
             // server_jar: msg.server_jar,
             name: name.to_owned(),
             mods: msg.url,
             max_memory: msg.max_memory,
             memory: None,
-            ports: msg.ports,
+            ports: ports.clone(),
             java_args: msg.java_args,
+            pack: None,
+            disk_quota: None,
+            stop_on_quota: false,
+            hostnames: Vec::new(),
+            command_rules: Vec::new(),
+            event_profile: msg.event_profile,
+            extra: Default::default(),
         };
 
-        let mut manifest = utils::open_manifest(&at)?;
-        desc.flush(&mut manifest)?;
-        drop(manifest);
+        if let Err(e) = desc.flush(&mut model::Manifest::at(&at)) {
+            self.free_ports(&ports);
+            return Err(e);
+        }
 
-        let env = instance::InstanceEnv {
-            timeout: self.timeout,
-            servers: ctx.address(),
-            password: self.password.clone(),
-        };
+        let env = self.env(ctx);
 
         match instance::Instance::load(Arc::clone(&at),env) {
             Ok((instance,ports)) => {
@@ -247,7 +534,117 @@ impl Handler<native_messages::InitServer<ReNewServer>> for Servers {
                 
                 Ok(())
             },
-            Err(e) => Err(anyhow!("couldn't reload server: {:?}", e)),
+            Err(e) => {
+                self.free_ports(&ports);
+                Err(anyhow!("couldn't reload server: {:?}", e))
+            },
+        }
+    }
+}
+
+impl Handler<native_messages::ProposeRepair> for Servers {
+    type Result = Option<model::RepairProposal>;
+
+    fn handle(&mut self, msg: native_messages::ProposeRepair, _: &mut Self::Context) -> Self::Result {
+        let at = self.resolve(&msg.name)?;
+        let bs = self.broken.iter().find(|b| b.at.as_ref() == at.as_path())?;
+
+        let mut proposal = infer::propose(&bs.at, bs.had.as_ref());
+
+        match proposal.port {
+            Some(port) => if let Err(e) = self.port_range.check(port) {
+                proposal.problems.push(format!("port {}: {}", port, e));
+            },
+            None => proposal.problems.push("couldn't find port".to_owned()),
+        }
+
+        match proposal.rcon {
+            Some(rcon) => if let Err(e) = self.rcon_range.check(rcon) {
+                proposal.problems.push(format!("rcon port {}: {}", rcon, e));
+            },
+            None => proposal.problems.push("couldn't find rcon port".to_owned()),
+        }
+
+        Some(proposal)
+    }
+}
+
+impl Handler<native_messages::ListQuarantined> for Servers {
+    type Result = Vec<model::QuarantinedServer>;
+
+    fn handle(&mut self, _: native_messages::ListQuarantined, _: &mut Self::Context) -> Self::Result {
+        let Ok(entries) = std::fs::read_dir(self.quarantine_dir()) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .map(|e| infer::infer(e.path()).quarantined(e.file_name().to_string_lossy().into_owned()))
+            .collect()
+    }
+}
+
+/// quarantined directory, and the name to adopt it under
+pub struct AdoptServer(pub String, pub String);
+
+impl Handler<native_messages::InitServer<AdoptServer>> for Servers {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: native_messages::InitServer<AdoptServer>, ctx: &mut Self::Context) -> Self::Result {
+        let dir = msg.ext.0.as_str();
+        let name = msg.ext.1.as_str();
+
+        let from = self.quarantine_dir().join(dir);
+        if dir.is_empty() || dir.contains('/') || dir.starts_with('.') || !from.is_dir() {
+            return Err(anyhow!("no such quarantined directory"));
+        }
+
+        self.check_free_name(name)?;
+
+        let id = uuid::Uuid::new_v4();
+        let target = self.id_to_path(id);
+
+        let ports = self.reserve_ports(msg.ports)?;
+
+        let desc: model::InstanceDescriptor = model::InstanceDescriptor {
+            schema_version: migrations::SCHEMA_VERSION,
+            name: name.to_owned(),
+            mods: msg.url,
+            max_memory: msg.max_memory,
+            memory: None,
+            ports: ports.clone(),
+            java_args: msg.java_args,
+            pack: None,
+            disk_quota: None,
+            stop_on_quota: false,
+            hostnames: Vec::new(),
+            command_rules: Vec::new(),
+            event_profile: msg.event_profile,
+            extra: Default::default(),
+        };
+
+        let adopt = || -> anyhow::Result<()> {
+            std::fs::rename(&from, &target)?;
+            desc.flush(&mut model::Manifest::at(&target))
+        };
+
+        if let Err(e) = adopt() {
+            self.free_ports(&ports);
+            return Err(e);
+        }
+
+        log::info!("adopted {:?} as {} with id {}", &from, name, id);
+
+        let at: Arc<Path> = target.into();
+
+        match instance::Instance::load(Arc::clone(&at),self.env(ctx)) {
+            Ok((instance,ports)) => {
+                self.names.insert(name.to_owned(), id);
+                self.add_instance(at, instance, ports);
+                Ok(())
+            },
+            Err(e) => Err(anyhow!("couldn't load adopted server: {:?}", e)),
         }
     }
 }
@@ -256,8 +653,18 @@ impl Handler<native_messages::AddrOf<instance::Instance>> for Servers {
     type Result = Option<Addr<instance::Instance>>;
 
     fn handle(&mut self, msg: native_messages::AddrOf<instance::Instance>, _: &mut Self::Context) -> Self::Result {
-        let name = self.name_to_path(msg.0);
-        self.servers.get_mut::<Path>(name.as_ref()).map(|s| s.addr.clone())
+        let path = self.resolve(&msg.0)?;
+        self.servers.get_mut::<Path>(path.as_ref()).map(|s| s.addr.clone())
+    }
+}
+
+impl Handler<native_messages::Route> for Servers {
+    type Result = Option<u16>;
+
+    fn handle(&mut self, msg: native_messages::Route, _: &mut Self::Context) -> Self::Result {
+        self.servers.values()
+            .find(|srv| srv.hostnames.contains(&msg.host))
+            .map(|srv| srv.ports.port)
     }
 }
 
@@ -267,9 +674,20 @@ impl Handler<native_messages::Ports> for Servers {
     fn handle(&mut self, _: native_messages::Ports, _: &mut Self::Context) -> Self::Result {
         let pr = self.port_range.range();
         let rr = self.rcon_range.range();
+
+        let extra = self.names.iter()
+            .filter_map(|(name, id)| Some((name, self.servers.get::<Path>(self.id_to_path(*id).as_ref())?)))
+            .flat_map(|(name, srv)| srv.ports.extra.iter().map(move |(n, p)| model::NamedPort {
+                server: name.clone(),
+                name: n.clone(),
+                port: *p,
+            }))
+            .collect();
+
         MessageResult(model::PortsInfo {
             ports: self.port_range.taken(),
             rcons: self.rcon_range.taken(),
+            extra,
             port_limits: [pr.start, pr.end],
             rcon_limits: [rr.start, rr.end],
         })
@@ -322,28 +740,34 @@ impl Handler<native_messages::InitServer<NewServer>> for Servers {
     fn handle(&mut self, msg: native_messages::InitServer<NewServer>, ctx: &mut Self::Context) -> Self::Result {
         let name = msg.ext.0.as_str();
 
-        let path = self.name_to_path(name);
+        self.check_free_name(name)?;
+        self.check_event_profile(msg.event_profile.as_deref())?;
 
-        if path.exists() && self.servers.contains_key((&*path).into()) {
-            return Err(anyhow!("server name is already in use"));
-        }
+        let id = uuid::Uuid::new_v4();
+        let path = self.id_to_path(id);
 
         // log::trace!("creating server: {:?}", &msg);
 
-        if !self.take_ports(&msg.ports) {
-            return Err(anyhow!("couldn't take ports"));
-        }
+        let ports = self.reserve_ports(msg.ports)?;
 
-        log::info!("create server at {:?}", &*path);
+        log::info!("create server {} at {:?}", name, &*path);
 
         let desc: model::InstanceDescriptor = model::InstanceDescriptor {
+            schema_version: migrations::SCHEMA_VERSION,
             // server_jar: msg.server_jar,
             name: name.to_owned(),
             mods: msg.url,
             max_memory: msg.max_memory,
             memory: None,
-            ports: msg.ports,
+            ports: ports.clone(),
             java_args: msg.java_args,
+            pack: None,
+            disk_quota: None,
+            stop_on_quota: false,
+            hostnames: Vec::new(),
+            command_rules: Vec::new(),
+            event_profile: msg.event_profile,
+            extra: Default::default(),
         };
 
         let instance_place: Arc<Path> = path.into();
@@ -355,73 +779,248 @@ impl Handler<native_messages::InitServer<NewServer>> for Servers {
             desc,
             // msg.setup_cmd,
             iu,
-            instance::InstanceEnv { 
-                servers: ctx.address(),
-                timeout: self.timeout,
-                password: self.password.clone(),
-            },
+            self.env(ctx),
         );
 
-        self.add_instance(instance_place, instance, msg.ports);
+        self.names.insert(name.to_owned(), id);
+        self.add_instance(instance_place, instance, ports);
 
         Ok(())
     }
 }
 
 impl Handler<native_messages::AlterServer> for Servers {
-    type Result = anyhow::Result<()>;
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
 
     fn handle(&mut self, msg: native_messages::AlterServer, _: &mut Self::Context) -> Self::Result {
-        let path = self.name_to_path(msg.name);
+        let Some(path) = self.resolve(&msg.name) else {
+            return Box::pin(fut::ready(Err(anyhow!("server not found"))));
+        };
 
-        let Some(srv) = self.servers.get_mut::<Path>(path.as_ref()) else {
-            return Err(anyhow!("server not found"));
+        let Some(srv) = self.servers.get::<Path>(path.as_ref()) else {
+            return Box::pin(fut::ready(Err(anyhow!("server not found"))));
         };
 
-        if let Some(port) = msg.msg.port {
-            if srv.ports.port != port {
-                self.port_range.try_take(port)?;
-                self.port_range.free(srv.ports.port)?;
-                srv.ports.port = port;
+        let addr = srv.addr.clone();
+        let old = srv.ports.clone();
+
+        let mut taken = Vec::new();
+        let new = match self.change_into(&old, msg.ports, &mut taken) {
+            Ok(new) => new,
+            Err(e) => {
+                self.release(&taken);
+                return Box::pin(fut::ready(Err(e)));
             }
+        };
+
+        let mut change = msg.msg;
+        change.ports = Some(new.clone());
+
+        if let Err(e) = self.check_event_profile(change.event_profile.as_deref()) {
+            self.release(&taken);
+            return Box::pin(fut::ready(Err(e)));
         }
 
-        //we don't really have to check for success here
-        let _ = srv.addr.send(msg.msg);
-        
-        Ok(())
+        if let Some(hostnames) = &mut change.hostnames {
+            if let Err(e) = self.check_hostnames(&path, hostnames) {
+                self.release(&taken);
+                return Box::pin(fut::ready(Err(e)));
+            }
+        }
+        let hostnames = change.hostnames.clone();
+
+        // ports are moved only once the instance accepts the change
+        Box::pin(addr.send(change).into_actor(self).map(move |res, this, _| {
+            if let Err(e) = res.map_err(anyhow::Error::from).and_then(|r| r) {
+                this.release(&taken);
+                return Err(e);
+            }
+
+            for port in old.game_ports().filter(|p| !new.game_ports().any(|n| n == *p)) {
+                let _ = this.port_range.free(port);
+            }
+            if old.rcon != new.rcon {
+                let _ = this.rcon_range.free(old.rcon);
+            }
+
+            if let Some(srv) = this.servers.get_mut::<Path>(path.as_ref()) {
+                srv.ports = new;
+                if let Some(hostnames) = hostnames {
+                    srv.hostnames = hostnames;
+                }
+            }
+
+            Ok(())
+        }))
     }
 }
 
 impl Handler<messages::Tick> for Servers {
     type Result = MessageResult<messages::Tick>;
 
-    fn handle(&mut self, _: messages::Tick, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, _: messages::Tick, ctx: &mut Self::Context) -> Self::Result {
         log::trace!("tick tac");
         self.hb();
+
+        self.ticks = self.ticks.wrapping_add(1);
+        if self.ticks.is_multiple_of(PURGE_EVERY_TICKS) && !self.purge_running {
+            self.purge_running = true;
+
+            let trash = self.trash_dir();
+            let retention = self.trash_retention;
+            let purge = tokio::task::spawn_blocking(move || trash::purge_expired(&trash, retention));
+
+            ctx.spawn(purge.into_actor(self).map(|_, this, _| {
+                this.purge_running = false;
+            }));
+        }
+
         MessageResult(())
     }
 }
 
 impl Handler<native_messages::DeleteServer> for Servers {
-    type Result = anyhow::Result<()>;
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
 
     fn handle(&mut self, msg: native_messages::DeleteServer, _: &mut Self::Context) -> Self::Result {
-        let path = self.name_to_path(msg.name);
+        let Some(id) = self.names.get(&msg.name).copied() else {
+            return Box::pin(fut::ready(Err(anyhow!("server not found"))));
+        };
 
-        let Some((path,srv)) = self.servers.remove_entry::<Path>(path.as_ref()) else {
-            return Err(anyhow!("server not found"));
+        let path = self.id_to_path(id);
+
+        let Some(srv) = self.servers.get::<Path>(path.as_ref()) else {
+            return Box::pin(fut::ready(Err(anyhow!("server not found"))));
         };
 
-        self.port_range.free(srv.ports.port)?;
-        self.rcon_range.free(srv.ports.rcon)?;
+        Box::pin(srv.addr.send(instance_messages::Kill).into_actor(self).map(move |res, this, _| {
+            match res {
+                Ok(Err(e)) => log::error!("couldn't kill {}: {}", &msg.name, e),
+                // actor is gone, so is the process
+                Err(e) => log::error!("couldn't reach {}: {}", &msg.name, e),
+                Ok(Ok(())) => {},
+            }
+
+            // listed with its ports taken until nothing of the server is left in place, so deleting can be retried
+            let entry = trash::move_in(&this.trash_dir(), &path, this.trash_retention).inspect_err(|e| {
+                log::error!("cannot move {} to trash, it stays listed: {}", &msg.name, e);
+            })?;
+            log::info!("moved {} to trash as {}", &msg.name, &entry.id);
 
-        let _ = srv.addr.send(instance_messages::Kill);
+            this.names.retain(|_, other| *other != id);
+            if let Some(srv) = this.servers.remove::<Path>(path.as_ref()) {
+                this.free_ports(&srv.ports);
+            }
+            Ok(())
+        }))
+    }
+}
+
+impl Handler<native_messages::RenameServer> for Servers {
+    type Result = ResponseActFuture<Self, anyhow::Result<()>>;
+
+    fn handle(&mut self, msg: native_messages::RenameServer, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self.check_free_name(&msg.new) {
+            return Box::pin(fut::ready(Err(e)));
+        }
+
+        let Some(id) = self.names.get(&msg.old).copied() else {
+            return Box::pin(fut::ready(Err(anyhow!("server not found"))));
+        };
+
+        let Some(srv) = self.servers.get::<Path>(self.id_to_path(id).as_ref()) else {
+            return Box::pin(fut::ready(Err(anyhow!("server not found"))));
+        };
+
+        let rename = srv.addr.send(instance_messages::Rename { name: msg.new.clone() });
+
+        // hold both names until the manifest is updated
+        self.names.insert(msg.new.clone(), id);
+
+        Box::pin(rename.into_actor(self).map(move |res, this, _| {
+            let res = res.map_err(anyhow::Error::from).and_then(|r| r);
+
+            if let Err(e) = res {
+                this.names.remove(&msg.new);
+                return Err(e);
+            }
+
+            this.names.remove(&msg.old);
+            log::info!("renamed {} to {}", &msg.old, &msg.new);
+            Ok(())
+        }))
+    }
+}
 
-        self.nuke(path)?;
+impl Handler<native_messages::ListTrash> for Servers {
+    type Result = Vec<model::TrashEntry>;
 
+    fn handle(&mut self, _: native_messages::ListTrash, _: &mut Self::Context) -> Self::Result {
+        trash::list(&self.trash_dir(), self.trash_retention)
+    }
+}
+
+impl Handler<native_messages::RestoreDeleted> for Servers {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: native_messages::RestoreDeleted, ctx: &mut Self::Context) -> Self::Result {
+        let Some(entry) = trash::list(&self.trash_dir(), self.trash_retention).into_iter().find(|e| e.id == msg.id) else {
+            return Err(anyhow!("no such trash entry"));
+        };
+
+        if let Err(e) = self.check_free_name(&entry.name) {
+            return Err(anyhow!("cannot restore {}: {}", &entry.name, e));
+        }
+
+        let restored = trash::move_out(&self.trash_dir(), &entry.id, &self.servers_dir)?;
+
+        // deleted before servers had ids
+        let id = match restored.file_name().and_then(|d| uuid::Uuid::parse_str(&d.to_string_lossy()).ok()) {
+            Some(id) => id,
+            None => {
+                let id = uuid::Uuid::new_v4();
+                std::fs::rename(&restored, self.id_to_path(id))?;
+                id
+            }
+        };
+
+        let at: Arc<Path> = self.id_to_path(id).into();
+
+        let (instance, ports) = match instance::Instance::load(Arc::clone(&at), self.env(ctx)) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                let _ = std::fs::rename(&at, self.trash_dir().join(&entry.id));
+                return Err(anyhow!("couldn't load deleted server: {:?}", e));
+            }
+        };
+
+        if !self.take_ports(&ports) {
+            let _ = std::fs::rename(&at, self.trash_dir().join(&entry.id));
+            return Err(anyhow!("ports of the deleted server are taken"));
+        }
+
+        log::info!("restored {} from trash", &entry.name);
+        self.names.insert(instance.name(), id);
+        self.add_instance(at, instance, ports);
         Ok(())
-    
+    }
+}
+
+impl Handler<native_messages::PurgeTrash> for Servers {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: native_messages::PurgeTrash, _: &mut Self::Context) -> Self::Result {
+        let trash = self.trash_dir();
+
+        match msg.id {
+            Some(id) => trash::purge(&trash, &id),
+            None => {
+                for entry in trash::list(&trash, self.trash_retention) {
+                    trash::purge(&trash, &entry.id)?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
@@ -437,7 +1036,7 @@ impl Handler<native_messages::DataOfBroken> for Servers {
     type Result = Option<serde_json::Value>;
 
     fn handle(&mut self, msg: native_messages::DataOfBroken, _: &mut Self::Context) -> Self::Result {
-        let at = self.name_to_path(msg.name);
+        let at = self.resolve(&msg.name)?;
         log::info!("getting data of broken server: {:?}", &at);
         let Some(bs) = self.broken.iter().find(|b| &*b.at == &*at) else {
             return None;
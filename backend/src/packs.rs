@@ -0,0 +1,571 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::anyhow;
+use async_graphql::UploadValue;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha512;
+use zip::ZipArchive;
+
+use crate::{model, utils};
+
+const MODRINTH_INDEX: &str = "modrinth.index.json";
+const CURSEFORGE_MANIFEST: &str = "manifest.json";
+
+const CURSEFORGE_API: &str = "https://api.curseforge.com/v1";
+
+/// where files listed in packs are fetched from
+#[derive(Clone)]
+pub struct DownloadSource {
+    /// local directory looked up before going to network,
+    /// files are found by their sha1 or name, curseforge ones at `curseforge/<project>-<file>.jar`
+    /// with expected sha1 in `curseforge/<project>-<file>.jar.sha1` and file name in `curseforge/<project>-<file>.jar.name`
+    mirror: Option<PathBuf>,
+    /// never go to network
+    offline: bool,
+    curseforge_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl std::fmt::Debug for DownloadSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadSource")
+            .field("mirror", &self.mirror)
+            .field("offline", &self.offline)
+            .field("curseforge_key", &self.curseforge_key.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+impl DownloadSource {
+    pub fn new(mirror: Option<PathBuf>, offline: bool, curseforge_key: Option<String>) -> anyhow::Result<Self> {
+        let connector = native_tls::TlsConnector::new()?;
+
+        let agent = ureq::AgentBuilder::new()
+            .tls_connector(Arc::new(connector))
+            .user_agent(concat!("tema3210/msrvmanager/", env!("CARGO_PKG_VERSION")))
+            .build();
+
+        Ok(Self {
+            mirror,
+            offline,
+            curseforge_key,
+            agent,
+        })
+    }
+
+    fn lookup_mirror(&self, names: &[String]) -> Option<File> {
+        let mirror = self.mirror.as_ref()?;
+        names.iter().find_map(|n| File::open(mirror.join(n)).ok())
+    }
+
+    pub fn fetch(&self, url: &str) -> anyhow::Result<Box<dyn Read + Send + Sync>> {
+        if self.offline {
+            return Err(anyhow!("offline, not fetching {}", url));
+        }
+
+        log::info!("downloading {}", url);
+
+        Ok(self.agent.get(url).call()?.into_reader())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ModrinthIndex {
+    name: String,
+    #[serde(rename = "versionId")]
+    version_id: String,
+    files: Vec<ModrinthFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModrinthFile {
+    path: String,
+    hashes: ModrinthHashes,
+    env: Option<ModrinthEnv>,
+    downloads: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModrinthHashes {
+    sha1: String,
+    sha512: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModrinthEnv {
+    server: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CurseManifest {
+    name: String,
+    version: Option<String>,
+    files: Vec<CurseFile>,
+    overrides: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CurseFile {
+    #[serde(rename = "projectID")]
+    project_id: u64,
+    #[serde(rename = "fileID")]
+    file_id: u64,
+    #[serde(default = "yes")]
+    required: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug)]
+struct CurseApiFile {
+    data: CurseApiFileData,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CurseApiFileData {
+    file_name: String,
+    download_url: Option<String>,
+    hashes: Vec<CurseApiHash>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CurseApiHash {
+    value: String,
+    algo: u8,
+}
+
+/// pathes from packs must stay inside of the instance
+fn safe_join(at: &Path, rel: &str) -> anyhow::Result<PathBuf> {
+    let rel = Path::new(rel);
+    if rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(at.join(rel))
+    } else {
+        Err(anyhow!("pack refers to a path outside of the server: {:?}", rel))
+    }
+}
+
+/// copies `from` into `to` checking hashes on the way, partially written file is removed on failure
+fn store_verified(mut from: impl Read, to: &Path, sha1: &str, sha512: Option<&str>) -> anyhow::Result<()> {
+    if let Some(p) = to.parent() {
+        std::fs::create_dir_all(p)?;
+    }
+
+    let mut part = to.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let mut out = File::create(&part)?;
+
+    let mut h1 = Sha1::new();
+    let mut h512 = Sha512::new();
+    let mut buf = vec![0; 64 * 1024];
+
+    let res = (|| -> anyhow::Result<()> {
+        loop {
+            let n = from.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            h1.update(&buf[..n]);
+            h512.update(&buf[..n]);
+            out.write_all(&buf[..n])?;
+        }
+        out.sync_all()?;
+
+        let got = hex::encode(h1.finalize());
+        if !got.eq_ignore_ascii_case(sha1) {
+            return Err(anyhow!("sha1 mismatch for {:?}: expected {}, got {}", to, sha1, got));
+        }
+
+        if let Some(sha512) = sha512 {
+            let got = hex::encode(h512.finalize());
+            if !got.eq_ignore_ascii_case(sha512) {
+                return Err(anyhow!("sha512 mismatch for {:?}", to));
+            }
+        }
+
+        Ok(())
+    })();
+
+    match res {
+        Ok(()) => {
+            std::fs::rename(&part, to)?;
+            Ok(())
+        },
+        Err(e) => {
+            let _ = std::fs::remove_file(&part);
+            Err(e)
+        },
+    }
+}
+
+/// unpacks upload into the server directory, resolving files of modpacks
+/// this blocks thread
+pub fn install(at: impl AsRef<Path>, data: &mut UploadValue, source: &DownloadSource) -> anyhow::Result<Option<model::PackInfo>> {
+    let at = at.as_ref();
+    let mut archive = ZipArchive::new(&mut data.content)?;
+
+    if archive.index_for_name(MODRINTH_INDEX).is_some() {
+        let index: ModrinthIndex = serde_json::from_reader(archive.by_name(MODRINTH_INDEX)?)?;
+        return install_modrinth(at, &mut archive, index, source).map(Some);
+    }
+
+    if archive.index_for_name(CURSEFORGE_MANIFEST).is_some() {
+        let manifest: Result<CurseManifest, _> = serde_json::from_reader(archive.by_name(CURSEFORGE_MANIFEST)?);
+        if let Ok(manifest) = manifest {
+            return install_curseforge(at, &mut archive, manifest, source).map(Some);
+        }
+    }
+
+    utils::unpack_archive_at(at, &mut archive, None)?;
+    Ok(None)
+}
+
+fn install_modrinth<R: Read + Seek>(
+    at: &Path,
+    archive: &mut ZipArchive<R>,
+    index: ModrinthIndex,
+    source: &DownloadSource,
+) -> anyhow::Result<model::PackInfo> {
+    log::info!("installing modrinth pack {} {}", index.name, index.version_id);
+
+    // server overrides go last to win over common ones
+    utils::unpack_archive_at(at, archive, Some("overrides"))?;
+    utils::unpack_archive_at(at, archive, Some("server-overrides"))?;
+
+    for f in &index.files {
+        if f.env.as_ref().map(|e| e.server == "unsupported").unwrap_or(false) {
+            log::info!("skipping client only {}", f.path);
+            continue;
+        }
+
+        let to = safe_join(at, &f.path)?;
+
+        let file_name = Path::new(&f.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let sha1 = f.hashes.sha1.as_str();
+        let sha512 = f.hashes.sha512.as_deref();
+
+        if let Some(local) = source.lookup_mirror(&[sha1.to_owned(), file_name]) {
+            store_verified(local, &to, sha1, sha512)?;
+            continue;
+        }
+
+        let mut last = anyhow!("no download urls for {}", f.path);
+        let mut done = false;
+        for url in &f.downloads {
+            match source.fetch(url).and_then(|r| store_verified(r, &to, sha1, sha512)) {
+                Ok(()) => {
+                    done = true;
+                    break;
+                },
+                Err(e) => {
+                    log::warn!("cannot fetch {} from {}: {}", f.path, url, e);
+                    last = e;
+                },
+            }
+        }
+
+        if !done {
+            return Err(last);
+        }
+    }
+
+    Ok(model::PackInfo {
+        source: model::PackSource::Modrinth,
+        name: index.name,
+        version: Some(index.version_id),
+    })
+}
+
+fn install_curseforge<R: Read + Seek>(
+    at: &Path,
+    archive: &mut ZipArchive<R>,
+    manifest: CurseManifest,
+    source: &DownloadSource,
+) -> anyhow::Result<model::PackInfo> {
+    log::info!("installing curseforge pack {} {:?}", manifest.name, manifest.version);
+
+    let overrides = manifest.overrides.as_deref().unwrap_or("overrides");
+    utils::unpack_archive_at(at, archive, Some(overrides))?;
+
+    let mods = at.join("mods");
+
+    for f in manifest.files.iter().filter(|f| f.required) {
+        let key = format!("{}-{}.jar", f.project_id, f.file_id);
+
+        // mirror keeps the expected hash next to the jar
+        if let (Some(local), Some(mut sha1)) = (
+            source.lookup_mirror(&[format!("curseforge/{}", key)]),
+            source.lookup_mirror(&[format!("curseforge/{}.sha1", key)]),
+        ) {
+            let mut expected = String::new();
+            sha1.read_to_string(&mut expected)?;
+
+            // same name the api would give, so the jar doesn't depend on where it came from
+            let mut file_name = String::new();
+            if let Some(mut name) = source.lookup_mirror(&[format!("curseforge/{}.name", key)]) {
+                name.read_to_string(&mut file_name)?;
+            }
+
+            let file_name = match file_name.trim() {
+                "" => {
+                    log::warn!("mirror has no name for curseforge/{}, keeping it as is", key);
+                    key.as_str()
+                },
+                name => name,
+            };
+
+            store_verified(local, &safe_join(&mods, file_name)?, expected.trim(), None)?;
+            continue;
+        }
+
+        let Some(api_key) = source.curseforge_key.as_ref().filter(|_| !source.offline) else {
+            return Err(anyhow!("{} is not in the mirror and curseforge api is not available", key));
+        };
+
+        let url = format!("{}/mods/{}/files/{}", CURSEFORGE_API, f.project_id, f.file_id);
+        let meta: CurseApiFile = source
+            .agent
+            .get(&url)
+            .set("x-api-key", api_key)
+            .call()?
+            .into_json()?;
+
+        let Some(download) = meta.data.download_url else {
+            return Err(anyhow!(
+                "{} ({}) does not allow third party downloads, put it into the mirror as curseforge/{} with its name in curseforge/{}.name",
+                meta.data.file_name, key, key, key
+            ));
+        };
+
+        // algo 1 is sha1
+        let Some(sha1) = meta.data.hashes.iter().find(|h| h.algo == 1) else {
+            return Err(anyhow!("curseforge gave no sha1 for {}", meta.data.file_name));
+        };
+
+        let to = safe_join(&mods, &meta.data.file_name)?;
+        store_verified(source.fetch(&download)?, &to, &sha1.value, None)?;
+    }
+
+    Ok(model::PackInfo {
+        source: model::PackSource::CurseForge,
+        name: manifest.name,
+        version: manifest.version,
+    })
+}
+
+/// where built client packs are kept, relative to the server
+pub const CLIENT_PACK_DIR: &str = ".client-pack";
+
+/// globs of config files players get, one per line relative to the server, `#` starts a comment
+/// configs often hold credentials, so nothing is shipped without it
+pub const CLIENT_FILES: &str = "msrvClientFiles.txt";
+
+const CLIENT_DIRS: [&str; 1] = ["config"];
+
+/// forge and neoforge name configs only the server reads like this
+const SERVER_CONFIG_SUFFIX: &str = "-server.toml";
+
+/// `*` is any run of characters, `/` included
+fn glob_to_regex(glob: &str) -> anyhow::Result<regex::Regex> {
+    let mut re = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    regex::Regex::new(&re).map_err(|e| anyhow!("bad pattern {:?} in {}: {}", glob, CLIENT_FILES, e))
+}
+
+/// configs listed in `CLIENT_FILES`, server side ones are left out even when listed
+fn client_configs(at: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let allowed = match std::fs::read_to_string(at.join(CLIENT_FILES)) {
+        Ok(list) => list
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or("").trim())
+            .filter(|l| !l.is_empty())
+            .map(glob_to_regex)
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut configs = Vec::new();
+    for d in CLIENT_DIRS {
+        list_files(at, Path::new(d), &mut configs);
+    }
+
+    configs.retain(|file| {
+        let rel = file.to_string_lossy();
+        !rel.ends_with(SERVER_CONFIG_SUFFIX) && allowed.iter().any(|re| re.is_match(&rel))
+    });
+    // stable order, so is the key
+    configs.sort();
+
+    Ok(configs)
+}
+
+/// files under `rel`, relative to `root`
+fn list_files(root: &Path, rel: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(root.join(rel)) else {
+        return;
+    };
+
+    for e in entries.filter_map(|e| e.ok()) {
+        let rel = rel.join(e.file_name());
+        if e.path().is_dir() {
+            list_files(root, &rel, out);
+        } else {
+            out.push(rel);
+        }
+    }
+}
+
+/// builds zip players need to join, mods marked as server side are left out, configs only when listed in `CLIENT_FILES`
+/// pack is reused as long as the set of mods and configs stays the same
+/// this blocks thread
+pub fn client_pack(
+    at: impl AsRef<Path>,
+    name: &str,
+    pack: Option<&model::PackInfo>,
+    mods: &[model::ModInfo],
+    format: model::ClientPackFormat,
+) -> anyhow::Result<PathBuf> {
+    let at = at.as_ref();
+
+    let mut included: Vec<(&str, &str)> = mods
+        .iter()
+        .filter(|m| m.file.starts_with("mods/"))
+        .filter(|m| m.side != model::ModSide::Server)
+        .map(|m| (m.file.as_str(), m.hash.as_str()))
+        .collect();
+    included.sort();
+    included.dedup();
+
+    let configs = client_configs(at)?;
+
+    let mut hasher = Sha1::new();
+    hasher.update(format.extension());
+    for (file, hash) in &included {
+        hasher.update(format!("{}:{}\n", file, hash));
+    }
+    // configs are not hashed, edits show up in size or mtime
+    for file in &configs {
+        let meta = std::fs::metadata(at.join(file))?;
+        let modified = meta.modified()?.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        hasher.update(format!("{}:{}:{}\n", file.to_string_lossy(), meta.len(), modified.as_nanos()));
+    }
+    let key = hex::encode(hasher.finalize());
+
+    let dir = at.join(CLIENT_PACK_DIR);
+    let out = dir.join(format!("{}.{}", key, format.extension()));
+
+    if out.is_file() {
+        return Ok(out);
+    }
+
+    std::fs::create_dir_all(&dir)?;
+
+    // packs of previous mod sets are of no use anymore, one a concurrent build just finished stays
+    for e in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+        if e.path() != out && e.path().extension().map(|e| e == format.extension()).unwrap_or(false) {
+            let _ = std::fs::remove_file(e.path());
+        }
+    }
+
+    log::info!("building client pack of {} with {} mods", name, included.len());
+
+    // own temp file per build, concurrent requests for the same pack must not share it
+    let tmp = dir.join(format!("{}.{}.part", key, uuid::Uuid::new_v4()));
+
+    let build = || -> anyhow::Result<()> {
+        let mut zip = zip::ZipWriter::new(File::create(&tmp)?);
+        let options = zip::write::SimpleFileOptions::default();
+
+        let prefix = match format {
+            model::ClientPackFormat::Mrpack => {
+                let index = serde_json::json!({
+                    "formatVersion": 1,
+                    "game": "minecraft",
+                    "versionId": pack.and_then(|p| p.version.clone()).unwrap_or_else(|| key[..8].to_owned()),
+                    "name": pack.map(|p| p.name.as_str()).unwrap_or(name),
+                    "files": [],
+                    "dependencies": crate::preflight::detect_versions(at),
+                });
+                zip.start_file("modrinth.index.json", options)?;
+                serde_json::to_writer_pretty(&mut zip, &index)?;
+                "overrides/"
+            },
+            model::ClientPackFormat::Zip => "",
+        };
+
+        for (file, _) in &included {
+            zip.start_file(format!("{}{}", prefix, file), options)?;
+            std::io::copy(&mut File::open(at.join(file))?, &mut zip)?;
+        }
+
+        for file in &configs {
+            zip.start_file(format!("{}{}", prefix, file.to_string_lossy()), options)?;
+            std::io::copy(&mut File::open(at.join(file))?, &mut zip)?;
+        }
+
+        zip.finish()?.sync_all()?;
+        std::fs::rename(&tmp, &out)?;
+        Ok(())
+    };
+
+    if let Err(e) = build() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_client_configs_are_shipped() {
+        let dir = std::env::temp_dir().join(format!("msrv-packs-{}", uuid::Uuid::new_v4()));
+        for file in [
+            "config/jei/jei-client.toml",
+            "config/create-common.toml",
+            "config/create-server.toml",
+            "config/discord/webhook.json",
+            "config/database.properties",
+        ] {
+            std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(file), "x").unwrap();
+        }
+
+        // nothing without a list
+        assert!(client_configs(&dir).unwrap().is_empty());
+
+        std::fs::write(dir.join(CLIENT_FILES), "# shipped to players\nconfig/jei/*\nconfig/create-*\n").unwrap();
+
+        assert_eq!(client_configs(&dir).unwrap(), vec![
+            PathBuf::from("config/create-common.toml"),
+            PathBuf::from("config/jei/jei-client.toml"),
+        ]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use md5::{Digest, Md5};
+use serde_json::{json, Value};
+
+use crate::{instance, model, packs, properties::ServerProperties};
+
+pub const WHITELIST_FILE: &str = "whitelist.json";
+pub const OPS_FILE: &str = "ops.json";
+pub const BANNED_FILE: &str = "banned-players.json";
+/// names and uuids of everyone who has joined
+const USERCACHE_FILE: &str = "usercache.json";
+
+/// level given by `op`, unless `op-permission-level` says otherwise
+const DEFAULT_OP_LEVEL: u64 = 4;
+
+/// same rules mojang uses for account names
+pub fn validate_player_name(name: &str) -> anyhow::Result<()> {
+    let ok = (3..=16).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !ok {
+        return Err(anyhow!("bad player name: {:?}", name));
+    }
+    Ok(())
+}
+
+/// reasons go to a single console line
+fn clean_reason(reason: Option<&str>) -> Option<String> {
+    reason
+        .map(|r| r.replace(['\n', '\r'], " ").trim().to_owned())
+        .filter(|r| !r.is_empty())
+}
+
+pub fn command(action: model::PlayerAction, player: &str, reason: Option<&str>) -> String {
+    use model::PlayerAction::*;
+
+    let reason = clean_reason(reason);
+    let with_reason = |cmd: String| match &reason {
+        Some(r) => format!("{} {}", cmd, r),
+        None => cmd,
+    };
+
+    match action {
+        Kick => with_reason(format!("kick {}", player)),
+        Ban => with_reason(format!("ban {}", player)),
+        Pardon => format!("pardon {}", player),
+        WhitelistAdd => format!("whitelist add {}", player),
+        WhitelistRemove => format!("whitelist remove {}", player),
+        Op => format!("op {}", player),
+        Deop => format!("deop {}", player),
+    }
+}
+
+/// strips `§` formatting codes some servers put into command output
+fn strip_formatting(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// response to `list`, both `There are 1 of a max of 20 players online: a` and older `There are 1/20 players online:` forms
+pub fn parse_list(response: &str) -> anyhow::Result<model::OnlinePlayers> {
+    let response = strip_formatting(response);
+
+    let re = regex::Regex::new(r"There are (\d+)(?: of a max of |/)(\d+) players online:?\s*(.*)")?;
+    let caps = re.captures(&response).ok_or(anyhow!("unexpected list response: {:?}", &response))?;
+
+    let players = caps[3]
+        .split([',', '\n'])
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_owned())
+        .collect();
+
+    Ok(model::OnlinePlayers {
+        online: caps[1].parse()?,
+        max: caps[2].parse()?,
+        players,
+    })
+}
+
+fn read_list(at: &Path, file: &str) -> anyhow::Result<Vec<Value>> {
+    match std::fs::read_to_string(at.join(file)) {
+        Ok(raw) if raw.trim().is_empty() => Ok(Vec::new()),
+        Ok(raw) => Ok(serde_json::from_str(&raw)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_list(at: &Path, file: &str, list: &[Value]) -> anyhow::Result<()> {
+    let tmp = at.join(format!("{}.tmp", file));
+    std::fs::write(&tmp, serde_json::to_string_pretty(list)?)?;
+    std::fs::rename(tmp, at.join(file))?;
+    Ok(())
+}
+
+fn name_of(entry: &Value) -> Option<&str> {
+    entry.get("name")?.as_str()
+}
+
+fn entries(at: &Path, file: &str) -> anyhow::Result<Vec<model::PlayerEntry>> {
+    Ok(read_list(at, file)?
+        .iter()
+        .filter_map(|e| Some(model::PlayerEntry {
+            name: name_of(e)?.to_owned(),
+            uuid: e.get("uuid").and_then(|u| u.as_str()).unwrap_or_default().to_owned(),
+            reason: e.get("reason").and_then(|r| r.as_str()).map(|r| r.to_owned()),
+        }))
+        .collect())
+}
+
+/// as written by the server, they are up to date whether it runs or not
+/// this blocks thread
+pub fn lists(at: &Path) -> anyhow::Result<model::PlayerLists> {
+    Ok(model::PlayerLists {
+        whitelist: entries(at, WHITELIST_FILE)?,
+        ops: entries(at, OPS_FILE)?,
+        banned: entries(at, BANNED_FILE)?,
+    })
+}
+
+/// java's `UUID.nameUUIDFromBytes("OfflinePlayer:" + name)`
+fn offline_uuid(name: &str) -> uuid::Uuid {
+    let mut hash: [u8; 16] = Md5::digest(format!("OfflinePlayer:{}", name)).into();
+    hash[6] = (hash[6] & 0x0f) | 0x30;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+    uuid::Uuid::from_bytes(hash)
+}
+
+#[derive(serde::Deserialize)]
+struct Profile {
+    id: String,
+    name: String,
+}
+
+/// usercache first, then how the server itself would do it; returns the name with proper case too
+fn resolve(at: &Path, player: &str, downloads: &packs::DownloadSource) -> anyhow::Result<(String, uuid::Uuid)> {
+    let cached = read_list(at, USERCACHE_FILE).unwrap_or_default();
+    if let Some(e) = cached.iter().find(|e| name_of(e).map(|n| n.eq_ignore_ascii_case(player)).unwrap_or(false)) {
+        if let Some(Ok(id)) = e.get("uuid").and_then(|u| u.as_str()).map(uuid::Uuid::parse_str) {
+            return Ok((name_of(e).unwrap_or(player).to_owned(), id));
+        }
+    }
+
+    let online = ServerProperties::load(at.join(instance::SERVER_PROPERTIES_FILE))
+        .ok()
+        .and_then(|p| p.get_parsed::<bool>("online-mode"))
+        .unwrap_or(true);
+
+    if !online {
+        return Ok((player.to_owned(), offline_uuid(player)));
+    }
+
+    let profile: Profile = serde_json::from_reader(
+        downloads.fetch(&format!("https://api.mojang.com/users/profiles/minecraft/{}", player))?
+    ).map_err(|e| anyhow!("no account named {}: {}", player, e))?;
+
+    Ok((profile.name, uuid::Uuid::parse_str(&profile.id)?))
+}
+
+/// `2024-01-31 12:00:00 +0000` as in ban lists
+fn format_time(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // days to civil date, from Howard Hinnant's algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} +0000", year, month, day, rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+/// edits the lists of a stopped server, this blocks on disk and maybe network
+pub fn apply_offline(
+    at: &Path,
+    action: model::PlayerAction,
+    player: &str,
+    reason: Option<&str>,
+    downloads: &packs::DownloadSource,
+) -> anyhow::Result<String> {
+    use model::PlayerAction::*;
+
+    let (file, adding) = match action {
+        Kick => return Err(anyhow!("cannot kick from a server that is not running")),
+        Ban => (BANNED_FILE, true),
+        Pardon => (BANNED_FILE, false),
+        WhitelistAdd => (WHITELIST_FILE, true),
+        WhitelistRemove => (WHITELIST_FILE, false),
+        Op => (OPS_FILE, true),
+        Deop => (OPS_FILE, false),
+    };
+
+    let mut list = read_list(at, file)?;
+    let is_player = |e: &Value| name_of(e).map(|n| n.eq_ignore_ascii_case(player)).unwrap_or(false);
+
+    if !adding {
+        let before = list.len();
+        list.retain(|e| !is_player(e));
+        if list.len() == before {
+            return Ok(format!("{} is not in {}", player, file));
+        }
+        write_list(at, file, &list)?;
+        return Ok(format!("removed {} from {}", player, file));
+    }
+
+    if list.iter().any(is_player) {
+        return Ok(format!("{} is already in {}", player, file));
+    }
+
+    let (name, id) = resolve(at, player, downloads)?;
+
+    let entry = match action {
+        Ban => json!({
+            "uuid": id.to_string(),
+            "name": name,
+            "created": format_time(crate::history::now()),
+            "source": "Server",
+            "expires": "forever",
+            "reason": clean_reason(reason).unwrap_or("Banned by an operator.".to_owned()),
+        }),
+        Op => json!({
+            "uuid": id.to_string(),
+            "name": name,
+            "level": ServerProperties::load(at.join(instance::SERVER_PROPERTIES_FILE))
+                .ok()
+                .and_then(|p| p.get_parsed::<u64>("op-permission-level"))
+                .unwrap_or(DEFAULT_OP_LEVEL),
+            "bypassesPlayerLimit": false,
+        }),
+        _ => json!({
+            "uuid": id.to_string(),
+            "name": name,
+        }),
+    };
+
+    list.push(entry);
+    write_list(at, file, &list)?;
+
+    Ok(format!("added {} to {}", name, file))
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+
+use crate::model::{CommandOrigin, CommandRule, PatternKind, RuleAction};
+
+/// used when no rules file is given, the manager stops servers itself so it knows about it
+/// rules are best effort, they see commands wrapped in `execute ... run` but not those run by functions or command blocks
+pub fn default_rules() -> Vec<CommandRule> {
+    vec![CommandRule {
+        action: RuleAction::Deny,
+        kind: PatternKind::Regex,
+        pattern: r"^stop(\s|$)".to_owned(),
+        origins: Vec::new(),
+    }]
+}
+
+/// json array of rules, checked after those of the instance
+pub fn load_rules(path: &Path) -> anyhow::Result<RuleSet> {
+    let rules: Vec<CommandRule> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    RuleSet::new(&rules)
+}
+
+/// slash and namespace are optional in console, rules see the command without them
+fn normalize(cmd: &str) -> &str {
+    let cmd = cmd.trim();
+    let cmd = cmd.strip_prefix('/').unwrap_or(cmd);
+    cmd.strip_prefix("minecraft:").unwrap_or(cmd)
+}
+
+/// the command itself and every one it runs through `execute ... run`
+fn commands(cmd: &str) -> Vec<&str> {
+    let mut found = vec![normalize(cmd)];
+
+    while let Some(last) = found.last() {
+        if !last.starts_with("execute ") {
+            break;
+        }
+        let Some((_, inner)) = last.split_once(" run ") else {
+            break;
+        };
+        found.push(normalize(inner));
+    }
+
+    found
+}
+
+/// `*` is any run of characters, `?` is one, the whole command has to match
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("(?i)^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+fn compile(rule: &CommandRule) -> anyhow::Result<regex::Regex> {
+    let re = match rule.kind {
+        PatternKind::Glob => glob_to_regex(&rule.pattern),
+        PatternKind::Regex => rule.pattern.clone(),
+    };
+    regex::Regex::new(&re).map_err(|e| anyhow!("bad pattern {:?}: {}", rule.pattern, e))
+}
+
+/// rules with their patterns compiled, built when rules are loaded or set
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<(CommandRule, regex::Regex)>,
+    /// set when stored rules don't compile, every command is denied then
+    broken: Option<String>,
+}
+
+impl RuleSet {
+    pub fn new(rules: &[CommandRule]) -> anyhow::Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|r| compile(r).map(|re| (r.clone(), re)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { rules, broken: None })
+    }
+
+    /// for rules already stored, a bad one shouldn't let everything through
+    pub fn or_deny_all(rules: &[CommandRule]) -> Self {
+        Self::new(rules).unwrap_or_else(|e| {
+            log::error!("command rules don't compile, denying every command: {}", e);
+            Self { rules: Vec::new(), broken: Some(e.to_string()) }
+        })
+    }
+}
+
+/// first matching rule decides, instance rules go before the global ones, unmatched commands are allowed
+pub fn check(instance: &RuleSet, global: &RuleSet, origin: CommandOrigin, cmd: &str) -> anyhow::Result<()> {
+    if let Some(e) = instance.broken.as_ref().or(global.broken.as_ref()) {
+        return Err(anyhow!("command rules are broken: {}", e));
+    }
+
+    let cmds = commands(cmd);
+
+    for (rule, re) in instance.rules.iter().chain(&global.rules) {
+        if !rule.origins.is_empty() && !rule.origins.contains(&origin) {
+            continue;
+        }
+
+        if !cmds.iter().any(|c| re.is_match(c)) {
+            continue;
+        }
+
+        return match rule.action {
+            RuleAction::Allow => Ok(()),
+            RuleAction::Deny => Err(anyhow!("command {:?} is denied by rule {:?}", cmds[0], rule.pattern)),
+        };
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> RuleSet {
+        RuleSet::new(&default_rules()).unwrap()
+    }
+
+    #[test]
+    fn stop_is_denied_however_it_is_wrapped() {
+        let none = RuleSet::default();
+        for cmd in ["stop", "/stop", "minecraft:stop", "execute run stop", "execute as @a at @s run stop", "execute run execute run /stop"] {
+            assert!(check(&none, &defaults(), CommandOrigin::Api, cmd).is_err(), "{}", cmd);
+        }
+        for cmd in ["stopsound @a", "say stop", "execute as @a run say stop"] {
+            assert!(check(&none, &defaults(), CommandOrigin::Api, cmd).is_ok(), "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn instance_rules_go_first_and_respect_origins() {
+        let allow = RuleSet::new(&[CommandRule {
+            action: RuleAction::Allow,
+            kind: PatternKind::Glob,
+            pattern: "stop".to_owned(),
+            origins: vec![CommandOrigin::Console],
+        }]).unwrap();
+
+        assert!(check(&allow, &defaults(), CommandOrigin::Console, "stop").is_ok());
+        assert!(check(&allow, &defaults(), CommandOrigin::Api, "stop").is_err());
+    }
+
+    #[test]
+    fn broken_rules_deny_everything() {
+        let broken = RuleSet::or_deny_all(&[CommandRule {
+            action: RuleAction::Deny,
+            kind: PatternKind::Regex,
+            pattern: "(".to_owned(),
+            origins: vec![],
+        }]);
+
+        assert!(check(&broken, &RuleSet::default(), CommandOrigin::Api, "list").is_err());
+    }
+}
@@ -0,0 +1,531 @@
+use std::{cmp::Ordering, collections::HashMap, path::Path};
+
+use crate::model::{
+    DependencyKind, IssueKind, IssueSeverity, ModInfo, ModLoader, ModSide, PreflightIssue, PreflightReport,
+};
+
+/// ids provided by the platform itself, never present as jars
+const PLATFORM_IDS: &[&str] = &[
+    "minecraft",
+    "java",
+    "forge",
+    "neoforge",
+    "fml",
+    "javafml",
+    "fabricloader",
+    "fabric-loader",
+    "quilt_loader",
+];
+
+/// guess loader of the server by the libraries it ships with
+pub fn detect_loader(at: impl AsRef<Path>) -> Option<ModLoader> {
+    let libs = at.as_ref().join("libraries");
+
+    let known = [
+        ("net/neoforged/neoforge", ModLoader::NeoForge),
+        ("net/neoforged/forge", ModLoader::NeoForge),
+        ("net/minecraftforge/forge", ModLoader::Forge),
+        ("org/quiltmc/quilt-loader", ModLoader::Quilt),
+        ("net/fabricmc/fabric-loader", ModLoader::Fabric),
+    ];
+
+    known
+        .into_iter()
+        .find(|(p, _)| libs.join(p).is_dir())
+        .map(|(_, l)| l)
+}
+
+/// versions of minecraft and loader as found in `libraries`, keyed by modrinth dependency names
+pub fn detect_versions(at: impl AsRef<Path>) -> HashMap<String, String> {
+    let libs = at.as_ref().join("libraries");
+
+    let first_dir = |p: &str| -> Option<String> {
+        let mut names: Vec<String> = std::fs::read_dir(libs.join(p))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names.pop()
+    };
+
+    let mut out = HashMap::new();
+
+    if let Some(v) = first_dir("net/minecraft/server").or_else(|| first_dir("net/fabricmc/intermediary")) {
+        out.insert("minecraft".to_owned(), v);
+    }
+
+    // forge versions are `<minecraft>-<forge>`
+    if let Some((mc, v)) = first_dir("net/minecraftforge/forge").as_deref().and_then(|v| v.split_once('-')) {
+        out.entry("minecraft".to_owned()).or_insert_with(|| mc.to_owned());
+        out.insert("forge".to_owned(), v.to_owned());
+    }
+
+    // neoforge `20.4.80` is for minecraft `1.20.4`
+    if let Some(v) = first_dir("net/neoforged/neoforge") {
+        let mut parts = v.split('.');
+        if let (Some(major), Some(minor)) = (parts.next(), parts.next()) {
+            let mc = if minor == "0" { format!("1.{}", major) } else { format!("1.{}.{}", major, minor) };
+            out.entry("minecraft".to_owned()).or_insert(mc);
+        }
+        out.insert("neoforge".to_owned(), v);
+    }
+
+    if let Some(v) = first_dir("net/fabricmc/fabric-loader") {
+        out.insert("fabric-loader".to_owned(), v);
+    }
+
+    if let Some(v) = first_dir("org/quiltmc/quilt-loader") {
+        out.insert("quilt-loader".to_owned(), v);
+    }
+
+    out
+}
+
+/// whether mod built for `what` can be loaded by `by`
+fn loader_accepts(by: ModLoader, what: ModLoader) -> Option<bool> {
+    use ModLoader::*;
+    match (by, what) {
+        (_, Unknown) | (Unknown, _) => None,
+        (a, b) if a == b => Some(true),
+        // neoforge of 1.20.1 still loads plain forge mods
+        (NeoForge, Forge) => None,
+        (Quilt, Fabric) => Some(true),
+        _ => Some(false),
+    }
+}
+
+fn issue(severity: IssueSeverity, kind: IssueKind, m: &ModInfo, message: String) -> PreflightIssue {
+    PreflightIssue {
+        severity,
+        kind,
+        mod_id: m.mod_id.clone(),
+        file: m.file.clone(),
+        message,
+    }
+}
+
+/// checks a set of mods of the server before it is started
+pub fn analyze(loader: Option<ModLoader>, mods: &[ModInfo]) -> PreflightReport {
+    let mut issues = Vec::new();
+
+    // only things in mods/ are loaded by the mod loader
+    let loaded: Vec<&ModInfo> = mods.iter().filter(|m| m.file.starts_with("mods/")).collect();
+
+    let loader = loader.or_else(|| {
+        // majority vote if libraries tell us nothing
+        let mut votes: HashMap<ModLoader, usize> = HashMap::new();
+        for m in loaded.iter().filter(|m| m.loader != ModLoader::Unknown) {
+            *votes.entry(m.loader).or_default() += 1;
+        }
+        votes.into_iter().max_by_key(|(_, n)| *n).map(|(l, _)| l)
+    });
+
+    let mut by_id: HashMap<&str, Vec<&ModInfo>> = HashMap::new();
+    for m in mods.iter().filter(|m| m.loader != ModLoader::Unknown) {
+        by_id.entry(m.mod_id.as_str()).or_default().push(m);
+        for p in &m.provides {
+            by_id.entry(p.as_str()).or_default();
+        }
+    }
+
+    for (id, ms) in &by_id {
+        let files: Vec<_> = ms.iter().map(|m| m.file.as_str()).collect::<std::collections::BTreeSet<_>>().into_iter().collect();
+        if files.len() > 1 {
+            issues.push(issue(
+                IssueSeverity::Error,
+                IssueKind::DuplicateMod,
+                ms[0],
+                format!("mod {} is present in several jars: {}", id, files.join(", ")),
+            ));
+        }
+    }
+
+    for m in &loaded {
+        if let Some(loader) = loader {
+            match loader_accepts(loader, m.loader) {
+                Some(false) => issues.push(issue(
+                    IssueSeverity::Error,
+                    IssueKind::LoaderMismatch,
+                    m,
+                    format!("{} is a {:?} mod, but server runs {:?}", m.name, m.loader, loader),
+                )),
+                None if m.loader != ModLoader::Unknown && m.loader != loader => issues.push(issue(
+                    IssueSeverity::Warning,
+                    IssueKind::LoaderMismatch,
+                    m,
+                    format!("{} is a {:?} mod, server runs {:?}", m.name, m.loader, loader),
+                )),
+                _ => {}
+            }
+        }
+
+        if m.side == ModSide::Client {
+            issues.push(issue(
+                IssueSeverity::Warning,
+                IssueKind::ClientOnly,
+                m,
+                format!("{} is a client side mod", m.name),
+            ));
+        }
+    }
+
+    for m in mods {
+        for d in &m.dependencies {
+            if d.side == ModSide::Client || PLATFORM_IDS.contains(&d.mod_id.as_str()) {
+                continue;
+            }
+
+            let present = by_id.get(d.mod_id.as_str());
+
+            match d.kind {
+                DependencyKind::Required => {
+                    let Some(present) = present else {
+                        issues.push(issue(
+                            IssueSeverity::Error,
+                            IssueKind::MissingDependency,
+                            m,
+                            format!(
+                                "{} requires {}{}",
+                                m.name,
+                                d.mod_id,
+                                d.version_range.as_ref().map(|r| format!(" {}", r)).unwrap_or_default()
+                            ),
+                        ));
+                        continue;
+                    };
+
+                    let (Some(range), Some(dep)) = (&d.version_range, present.first()) else {
+                        continue;
+                    };
+
+                    let Some(version) = &dep.version else {
+                        continue;
+                    };
+
+                    if version_matches(range, version, m.loader) == Some(false) {
+                        issues.push(issue(
+                            IssueSeverity::Error,
+                            IssueKind::VersionMismatch,
+                            m,
+                            format!("{} requires {} {}, found {}", m.name, d.mod_id, range, version),
+                        ));
+                    }
+                },
+                DependencyKind::Incompatible => {
+                    let Some(dep) = present.and_then(|p| p.first()) else {
+                        continue;
+                    };
+
+                    let hit = match (&d.version_range, &dep.version) {
+                        (Some(range), Some(version)) => version_matches(range, version, m.loader),
+                        _ => Some(true),
+                    };
+
+                    match hit {
+                        Some(true) => issues.push(issue(
+                            IssueSeverity::Error,
+                            IssueKind::Incompatible,
+                            m,
+                            format!("{} is incompatible with {} {}", m.name, d.mod_id, dep.version.as_deref().unwrap_or("")),
+                        )),
+                        // a range we can't read is not a reason to refuse the start
+                        None => issues.push(issue(
+                            IssueSeverity::Warning,
+                            IssueKind::Incompatible,
+                            m,
+                            format!(
+                                "{} may be incompatible with {} {}, cannot compare with {}",
+                                m.name,
+                                d.mod_id,
+                                dep.version.as_deref().unwrap_or(""),
+                                d.version_range.as_deref().unwrap_or("")
+                            ),
+                        )),
+                        Some(false) => {},
+                    }
+                },
+                DependencyKind::Optional => {},
+            }
+        }
+    }
+
+    issues.sort_by(|a, b| a.severity.cmp(&b.severity).then_with(|| a.file.cmp(&b.file)));
+
+    PreflightReport { loader, issues }
+}
+
+impl PreflightReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == IssueSeverity::Error)
+    }
+
+    pub fn summary(&self) -> String {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == IssueSeverity::Error)
+            .map(|i| i.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[derive(Debug)]
+struct Version {
+    nums: Vec<u64>,
+    pre: bool,
+}
+
+impl Version {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let s = s.split('+').next()?;
+        let (main, pre) = match s.split_once('-') {
+            // "1.20.1-4.3.2", minecraft version prepended to the mod's one
+            Some((mc, v)) if mc.starts_with("1.") && v.starts_with(|c: char| c.is_ascii_digit()) => {
+                return Self::parse(v);
+            },
+            Some((main, _)) => (main, true),
+            None => (s, false),
+        };
+
+        let nums = main
+            .split('.')
+            .map(|p| {
+                // "4a" -> 4, minecraft-like suffixes are not comparable anyway
+                let digits: String = p.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse().ok()
+            })
+            .collect::<Option<Vec<u64>>>()?;
+
+        if nums.is_empty() {
+            return None;
+        }
+
+        Some(Self { nums, pre })
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.nums.len().max(other.nums.len());
+        for i in 0..len {
+            let a = self.nums.get(i).copied().unwrap_or(0);
+            let b = other.nums.get(i).copied().unwrap_or(0);
+            match a.cmp(&b) {
+                Ordering::Equal => continue,
+                o => return o,
+            }
+        }
+        // pre-release goes before release
+        other.pre.cmp(&self.pre)
+    }
+}
+
+/// `None` when either range or version is not something we understand
+pub fn version_matches(range: &str, version: &str, loader: ModLoader) -> Option<bool> {
+    let range = range.trim();
+    if range.is_empty() || range == "*" {
+        return Some(true);
+    }
+
+    let version = Version::parse(version)?;
+
+    match loader {
+        ModLoader::Forge | ModLoader::NeoForge => maven_matches(range, &version),
+        _ => semver_matches(range, &version),
+    }
+}
+
+/// maven ranges, `[1.0,2.0)`, `[1.2,)`, `(,1.0],[1.2,)`, bare version is a soft requirement
+fn maven_matches(range: &str, version: &Version) -> Option<bool> {
+    if !range.starts_with('[') && !range.starts_with('(') {
+        return Some(true);
+    }
+
+    let mut any = false;
+    let mut rest = range;
+
+    while let Some(start) = rest.find(['[', '(']) {
+        let end = rest[start..].find([']', ')'])? + start;
+        let body = &rest[start + 1..end];
+        let lower_inclusive = &rest[start..start + 1] == "[";
+        let upper_inclusive = &rest[end..end + 1] == "]";
+
+        let ok = match body.split_once(',') {
+            None => Version::parse(body)? == *version,
+            Some((lo, hi)) => {
+                let lo_ok = match lo.trim() {
+                    "" => true,
+                    lo => {
+                        let lo = Version::parse(lo)?;
+                        if lower_inclusive { *version >= lo } else { *version > lo }
+                    },
+                };
+                let hi_ok = match hi.trim() {
+                    "" => true,
+                    hi => {
+                        let hi = Version::parse(hi)?;
+                        if upper_inclusive { *version <= hi } else { *version < hi }
+                    },
+                };
+                lo_ok && hi_ok
+            },
+        };
+
+        any |= ok;
+        rest = &rest[end + 1..];
+    }
+
+    Some(any)
+}
+
+/// fabric style predicates, `>=1.2 <2`, `~1.2`, `^1.2`, `1.2.x`, alternatives joined by `||`
+fn semver_matches(range: &str, version: &Version) -> Option<bool> {
+    let mut any = false;
+
+    for alt in range.split("||") {
+        let mut all = true;
+        for pred in alt.split_whitespace() {
+            all &= predicate_matches(pred, version)?;
+        }
+        any |= all;
+    }
+
+    Some(any)
+}
+
+fn predicate_matches(pred: &str, version: &Version) -> Option<bool> {
+    if pred == "*" {
+        return Some(true);
+    }
+
+    for (op, f) in [
+        (">=", Ordering::is_ge as fn(Ordering) -> bool),
+        ("<=", Ordering::is_le),
+        (">", Ordering::is_gt),
+        ("<", Ordering::is_lt),
+        ("=", Ordering::is_eq),
+    ] {
+        if let Some(v) = pred.strip_prefix(op) {
+            return Some(f(version.cmp(&Version::parse(v)?)));
+        }
+    }
+
+    // ~1.2.3 allows patch updates, ^1.2.3 allows minor updates
+    let (base, keep) = if let Some(v) = pred.strip_prefix('~') {
+        (v, 2)
+    } else if let Some(v) = pred.strip_prefix('^') {
+        (v, 1)
+    } else {
+        // 1.20.x or plain version
+        let fixed: Vec<&str> = pred.split('.').take_while(|p| *p != "x" && *p != "X" && *p != "*").collect();
+        if fixed.len() == pred.split('.').count() {
+            return Some(*version == Version::parse(pred)?);
+        }
+        let keep = fixed.len();
+        (pred, keep)
+    };
+
+    let lower = Version::parse(&base.replace(['x', 'X', '*'], "0"))?;
+    if *version < lower {
+        return Some(false);
+    }
+
+    let prefix_ok = (0..keep.min(lower.nums.len()))
+        .all(|i| version.nums.get(i).copied().unwrap_or(0) == lower.nums[i]);
+
+    Some(prefix_ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModDependency;
+
+    #[test]
+    fn maven_ranges() {
+        let forge = ModLoader::Forge;
+        assert_eq!(version_matches("[1.20,1.21)", "1.20", forge), Some(true));
+        assert_eq!(version_matches("[1.20,1.21)", "1.20.4", forge), Some(true));
+        assert_eq!(version_matches("[1.20,1.21)", "1.21", forge), Some(false));
+        assert_eq!(version_matches("[1.20,1.21)", "1.19.2", forge), Some(false));
+        assert_eq!(version_matches("(,1.0],[1.2,)", "1.1", forge), Some(false));
+        assert_eq!(version_matches("(,1.0],[1.2,)", "1.3", forge), Some(true));
+        // bare version is only a recommendation
+        assert_eq!(version_matches("1.5", "1.0", forge), Some(true));
+    }
+
+    #[test]
+    fn semver_ranges() {
+        let fabric = ModLoader::Fabric;
+        assert_eq!(version_matches(">=0.15", "0.15.11", fabric), Some(true));
+        assert_eq!(version_matches(">=0.15", "0.14.25", fabric), Some(false));
+        assert_eq!(version_matches(">=0.15 <1", "1.0.0", fabric), Some(false));
+        assert_eq!(version_matches("~1.2", "1.2.9", fabric), Some(true));
+        assert_eq!(version_matches("1.20.x", "1.20.1", fabric), Some(true));
+        assert_eq!(version_matches("1.20.x", "1.21", fabric), Some(false));
+    }
+
+    #[test]
+    fn any_version() {
+        assert_eq!(version_matches("*", "whatever", ModLoader::Forge), Some(true));
+        assert_eq!(version_matches("*", "1.0", ModLoader::Fabric), Some(true));
+        assert_eq!(version_matches("", "1.0", ModLoader::Fabric), Some(true));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(version_matches("[1.20,", "1.20", ModLoader::Forge), None);
+        assert_eq!(version_matches("[abc,1.0)", "0.5", ModLoader::Forge), None);
+        assert_eq!(version_matches(">=abc", "1.0", ModLoader::Fabric), None);
+        assert_eq!(version_matches("[1.0,2.0)", "not-a-version", ModLoader::Forge), None);
+    }
+
+    fn jar(id: &str, version: &str, deps: Vec<ModDependency>) -> ModInfo {
+        ModInfo {
+            file: format!("mods/{}.jar", id),
+            hash: String::new(),
+            mod_id: id.to_owned(),
+            name: id.to_owned(),
+            version: Some(version.to_owned()),
+            authors: vec![],
+            loader: ModLoader::Forge,
+            side: ModSide::Both,
+            dependencies: deps,
+            provides: vec![],
+        }
+    }
+
+    #[test]
+    fn unreadable_incompatible_range_is_a_warning() {
+        let mods = vec![
+            jar("a", "1.0", vec![ModDependency {
+                mod_id: "b".to_owned(),
+                kind: DependencyKind::Incompatible,
+                version_range: Some("[1.0,".to_owned()),
+                side: ModSide::Both,
+            }]),
+            jar("b", "1.0", vec![]),
+        ];
+
+        let report = analyze(Some(ModLoader::Forge), &mods);
+        assert!(!report.has_errors());
+        assert!(report.issues.iter().any(|i| i.kind == IssueKind::Incompatible && i.severity == IssueSeverity::Warning));
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+/// `server.properties`, kept line by line so comments and order survive rewrites
+#[derive(Debug, Default, Clone)]
+pub struct ServerProperties {
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone)]
+enum Line {
+    Entry(String, String),
+    Other(String),
+}
+
+impl ServerProperties {
+    pub fn parse(src: &str) -> Self {
+        let lines = src
+            .lines()
+            .map(|l| {
+                let trimmed = l.trim_start();
+                if trimmed.starts_with('#') || trimmed.starts_with('!') {
+                    return Line::Other(l.to_owned());
+                }
+                match l.split_once(['=', ':']) {
+                    Some((k, v)) => Line::Entry(k.trim().to_owned(), v.trim_start().to_owned()),
+                    None => Line::Other(l.to_owned()),
+                }
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    /// missing file reads as empty
+    pub fn load(at: impl AsRef<Path>) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(at.as_ref()) {
+            Ok(src) => Ok(Self::parse(&src)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|l| match l {
+            Line::Entry(k, v) if k == key => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn get_parsed<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.trim().parse().ok()
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        for l in &mut self.lines {
+            if let Line::Entry(k, v) = l {
+                if k == key {
+                    *v = value;
+                    return;
+                }
+            }
+        }
+        self.lines.push(Line::Entry(key.to_owned(), value));
+    }
+
+    pub fn save(&self, at: impl AsRef<Path>) -> anyhow::Result<()> {
+        let mut out = String::new();
+        for l in &self.lines {
+            match l {
+                Line::Entry(k, v) => {
+                    out.push_str(k);
+                    out.push('=');
+                    out.push_str(v);
+                },
+                Line::Other(o) => out.push_str(o),
+            }
+            out.push('\n');
+        }
+        std::fs::write(at, out)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use std::{net::Ipv4Addr, sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}};
+
+use crate::{messages::native_messages, native};
+
+/// clients that don't finish the handshake in time are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// handshake, status request and ping are all tiny
+const MAX_PACKET: usize = 1024;
+
+/// longest address the protocol allows
+const MAX_HOST: usize = 255;
+
+/// first byte of pre 1.7 server list ping
+const LEGACY_PING: u8 = 0xFE;
+
+pub struct ProxyConfig {
+    pub addr: Ipv4Addr,
+    pub port: u16,
+    /// shown in the server list for hosts no server claims
+    pub unknown_motd: String,
+}
+
+/// accepts connections forever, each is routed on its own task
+pub async fn run(config: ProxyConfig, servers: native::Service) -> anyhow::Result<()> {
+    let listener = TcpListener::bind((config.addr, config.port)).await?;
+    log::info!("minecraft proxy listening on {}:{}", config.addr, config.port);
+
+    let config = Arc::new(config);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                log::error!("proxy couldn't accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let servers = servers.clone();
+        let config = Arc::clone(&config);
+
+        actix::spawn(async move {
+            if let Err(e) = handle(stream, &servers, &config).await {
+                log::debug!("proxied connection from {} ended: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, servers: &native::Service, config: &ProxyConfig) -> anyhow::Result<()> {
+    // a client that sends nothing must not hold the connection either
+    let mut first = [0u8; 1];
+    if tokio::time::timeout(HANDSHAKE_TIMEOUT, stream.peek(&mut first)).await?? == 0 || first[0] == LEGACY_PING {
+        return Ok(());
+    }
+
+    let (raw, body) = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_packet(&mut stream)).await??;
+
+    let mut r = Reader(&body);
+    if r.varint()? != 0 {
+        return Err(anyhow!("not a handshake"));
+    }
+    let protocol = r.varint()?;
+    let host = normalize_host(&r.string(MAX_HOST)?);
+    let _port = r.u16()?;
+    let next_state = r.varint()?;
+
+    let route = servers.send(native_messages::Route { host: host.clone() }).await?;
+
+    let Some(port) = route else {
+        log::debug!("no server for host {:?}", &host);
+        return unknown_host(stream, protocol, next_state, config).await;
+    };
+
+    let mut upstream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await?;
+    upstream.write_all(&raw).await?;
+
+    tokio::io::copy_bidirectional(&mut stream, &mut upstream).await?;
+    Ok(())
+}
+
+/// answers server list ping with the configured motd, refuses logins with it
+async fn unknown_host(mut stream: TcpStream, protocol: i32, next_state: i32, config: &ProxyConfig) -> anyhow::Result<()> {
+    let text = serde_json::json!({ "text": config.unknown_motd });
+
+    if next_state != 1 {
+        // login disconnect
+        return write_packet(&mut stream, 0x00, &string(&text.to_string())).await;
+    }
+
+    let status = serde_json::json!({
+        "version": { "name": "msrvmanager", "protocol": protocol },
+        "players": { "max": 0, "online": 0 },
+        "description": text,
+    });
+
+    loop {
+        let (_, body) = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_packet(&mut stream)).await??;
+        let mut r = Reader(&body);
+
+        match r.varint()? {
+            0x00 => write_packet(&mut stream, 0x00, &string(&status.to_string())).await?,
+            0x01 => {
+                let payload = r.rest();
+                return write_packet(&mut stream, 0x01, payload).await;
+            },
+            id => return Err(anyhow!("unexpected status packet {}", id)),
+        }
+    }
+}
+
+/// forge appends its marker after a nul, srv lookups leave a trailing dot
+fn normalize_host(host: &str) -> String {
+    host.split('\0')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+/// returns the packet as read, with its length prefix, and its body
+async fn read_packet(stream: &mut TcpStream) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let mut raw = Vec::new();
+    let mut len: u32 = 0;
+
+    for i in 0..5 {
+        let b = stream.read_u8().await?;
+        raw.push(b);
+        len |= ((b & 0x7f) as u32) << (7 * i);
+        if b & 0x80 == 0 {
+            break;
+        }
+        if i == 4 {
+            return Err(anyhow!("packet length is too long"));
+        }
+    }
+
+    let len = len as usize;
+    if len == 0 || len > MAX_PACKET {
+        return Err(anyhow!("bad packet length {}", len));
+    }
+
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await?;
+    raw.extend_from_slice(&body);
+
+    Ok((raw, body))
+}
+
+async fn write_packet(stream: &mut TcpStream, id: i32, payload: &[u8]) -> anyhow::Result<()> {
+    let mut body = varint(id);
+    body.extend_from_slice(payload);
+
+    let mut packet = varint(body.len() as i32);
+    packet.extend_from_slice(&body);
+
+    stream.write_all(&packet).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+fn varint(value: i32) -> Vec<u8> {
+    let mut value = value as u32;
+    let mut out = Vec::with_capacity(5);
+    loop {
+        if value & !0x7f == 0 {
+            out.push(value as u8);
+            return out;
+        }
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn string(s: &str) -> Vec<u8> {
+    let mut out = varint(s.len() as i32);
+    out.extend_from_slice(s.as_bytes());
+    out
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(anyhow!("packet is too short"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.0)
+    }
+
+    fn varint(&mut self) -> anyhow::Result<i32> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let b = self.take(1)?[0];
+            value |= ((b & 0x7f) as u32) << (7 * i);
+            if b & 0x80 == 0 {
+                return Ok(value as i32);
+            }
+        }
+        Err(anyhow!("varint is too long"))
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn string(&mut self, max: usize) -> anyhow::Result<String> {
+        let len = self.varint()?;
+        let len = usize::try_from(len).map_err(|_| anyhow!("negative string length"))?;
+        // length is in utf-16 units, bytes can be up to 3 times that
+        if len > max * 3 {
+            return Err(anyhow!("string is too long"));
+        }
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
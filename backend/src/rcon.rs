@@ -1,28 +1,39 @@
-use anyhow::Result;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use futures::stream::{Stream, StreamExt};
 use tokio::task::JoinHandle;
+use std::collections::HashMap;
+use std::future::Future;
 use std::pin::Pin;
-use std::sync::atomic::AtomicI32;
+use std::sync::{Arc, Mutex};
 
 use actix::prelude::*;
 
+/// `epoch` is of the run the connection was made for
 #[derive(Message,Debug)]
-#[rtype(result = "anyhow::Result<()>")]
+#[rtype(result = "()")]
 pub struct RconUp {
-    pub rcon: Rcon
+    pub rcon: Rcon,
+    pub epoch: u32
 }
 
+/// connection was lost or couldn't be made, next attempt is after `retry`
 #[derive(Message,Debug)]
 #[rtype(result = "()")]
-pub struct RconDown;
+pub struct RconDown {
+    pub epoch: u32,
+    pub retry: std::time::Duration
+}
 
+/// responds with the output of the command
 #[derive(Message,Debug)]
-#[rtype(result = "anyhow::Result<()>")]
+#[rtype(result = "anyhow::Result<String>")]
 pub struct RconMessage {
-    pub cmd: String
+    pub cmd: String,
+    pub origin: crate::model::CommandOrigin,
+    pub caller: Option<String>
 }
 
 #[derive(Message)]
@@ -37,15 +48,36 @@ pub enum RconOutput {
     ConnectionClosed,
 }
 
+/// a command on its way to the server, `reply` is for whoever waits on the response
+struct Request {
+    cmd: String,
+    reply: oneshot::Sender<Result<String>>,
+}
+
+/// response being put together from fragments
+struct Pending {
+    body: Vec<u8>,
+    reply: oneshot::Sender<Result<String>>,
+}
+
+/// keyed by id of the command, its sentinel goes with the next id
+type PendingMap = Arc<Mutex<HashMap<i32, Pending>>>;
+
 #[derive(Debug)]
 pub struct Rcon {
-    command_sender: mpsc::Sender<String>, // Unicast channel
+    command_sender: mpsc::Sender<Request>, // Unicast channel
     output_receiver: broadcast::Receiver<RconOutput>, // Broadcast channel
 
     outgoing_task: JoinHandle<()>,
     incoming_task: JoinHandle<()>,
 }
 
+impl std::fmt::Debug for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Request").field("cmd", &self.cmd).finish()
+    }
+}
+
 impl Drop for Rcon {
     fn drop(&mut self) {
         self.outgoing_task.abort();
@@ -62,16 +94,41 @@ impl RconMessageType {
     const SERVERDATA_AUTH: Self = Self(3);
     const SERVERDATA_EXECCOMMAND: Self = Self(2);
     const SERVERDATA_AUTH_RESPONSE: Self = Self(2);
-    // const SERVERDATA_RESPONSE_VALUE: Self = Self(0);
+    const SERVERDATA_RESPONSE_VALUE: Self = Self(0);
 }
 
-impl Rcon {
+/// how long callers wait for response to their command
+pub const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// reconnection backs off from the first to the second
+pub const RETRY_MIN: std::time::Duration = std::time::Duration::from_secs(2);
+pub const RETRY_MAX: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// id, type and the two nul terminators
+const PACKET_OVERHEAD: i32 = 10;
+
+/// servers fragment at 4096 bytes of body, anything way bigger is garbage
+const MAX_PACKET: i32 = 64 * 1024;
+
+struct Packet {
+    id: i32,
+    kind: RconMessageType,
+    body: Vec<u8>,
+}
+
+/// odd ids are commands, the even one after each is its sentinel, so both have to fit
+fn next_id(id: i32) -> i32 {
+    match id.checked_add(2) {
+        Some(next) if next < i32::MAX => next,
+        _ => 1,
+    }
+}
 
-    const ORDER: std::sync::atomic::Ordering = std::sync::atomic::Ordering::SeqCst;
+impl Rcon {
 
-    fn build_packet(request_id: i32, packet_type: RconMessageType, body: String) -> Result<Vec<u8>> {
+    fn build_packet(request_id: i32, packet_type: RconMessageType, body: &str) -> Vec<u8> {
         let mut packet = Vec::new();
-        let size = (body.len() + 10) as i32;
+        let size = body.len() as i32 + PACKET_OVERHEAD;
         packet.extend_from_slice(&size.to_le_bytes());
         packet.extend_from_slice(&request_id.to_le_bytes());
         packet.extend_from_slice(&packet_type.0.to_le_bytes());
@@ -79,7 +136,25 @@ impl Rcon {
         packet.push(0);
         packet.push(0);
 
-        Ok(packet)
+        packet
+    }
+
+    async fn read_packet(stream: &mut (impl AsyncRead + Unpin)) -> Result<Packet> {
+        let size = stream.read_i32_le().await?;
+        if !(PACKET_OVERHEAD..=MAX_PACKET).contains(&size) {
+            anyhow::bail!("bad rcon packet size {}", size);
+        }
+
+        let mut packet = vec![0; size as usize];
+        stream.read_exact(&mut packet).await?;
+
+        // here we have 4bytes PID, 4bytes Type, Payload, 2 bytes null terminator
+        let id = i32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+        let kind = RconMessageType(i32::from_le_bytes([packet[4], packet[5], packet[6], packet[7]]));
+        packet.truncate(packet.len() - 2);
+        let body = packet.split_off(8);
+
+        Ok(Packet { id, kind, body })
     }
 }
 
@@ -87,89 +162,142 @@ impl Rcon {
 
     pub async fn new(port: u16, password: String) -> Result<Self> {
         let address = format!("127.0.0.1:{}", port);
-        let mut stream = TcpStream::connect(address).await?;
+        let stream = TcpStream::connect(address).await?;
+
+        let rcon = Self::over(stream, password).await?;
 
-        let auth_packet = Self::build_packet(0, RconMessageType::SERVERDATA_AUTH, password)?;
+        log::info!("rcon connection established: {}", port);
+        Ok(rcon)
+    }
+
+    async fn over<S>(mut stream: S, password: String) -> Result<Self>
+        where S: AsyncRead + AsyncWrite + Unpin + Send + 'static
+    {
+        let auth_packet = Self::build_packet(0, RconMessageType::SERVERDATA_AUTH, &password);
 
         stream.write_all(&auth_packet).await?;
         stream.flush().await?;
 
-        let mut response = [0; 14];
-        stream.read_exact(&mut response).await?;
+        //rcon protocol authentication, some servers send an empty response value first
+        let auth = loop {
+            let packet = Self::read_packet(&mut stream).await?;
+            if packet.kind == RconMessageType::SERVERDATA_AUTH_RESPONSE {
+                break packet;
+            }
+        };
 
-        //rcon protocol authentication
+        if auth.id == -1 {
+            anyhow::bail!("Authentication failed {}", auth.id);
+        }
 
-        // let received_length = i32::from_le_bytes([response[0], response[1], response[2], response[3]]);
-        let received_id = i32::from_le_bytes([response[4], response[5], response[6], response[7]]);
-        let response_type = RconMessageType(i32::from_le_bytes([response[8], response[9], response[10], response[11]]));
+        let (reader,writer) = tokio::io::split(stream);
 
-        let request_id = AtomicI32::new(1);
-        if received_id == 0 && response_type == RconMessageType::SERVERDATA_AUTH_RESPONSE {
-            let (reader,writer) = stream.into_split();
+        let (publish,subscribe) = broadcast::channel::<RconOutput>(100); //Initialize broadcast channel
+        let (mpsc_sender, mut msg_recv) = mpsc::channel::<Request>(100); // Initialize unicast channel
 
-            let (publish,subscribe) = broadcast::channel::<RconOutput>(100); //Initialize broadcast channel
-            let (mpsc_sender, mut msg_recv) = mpsc::channel::<String>(100); // Initialize unicast channel
+        let pending: PendingMap = Default::default();
 
-            let outgoing_task = tokio::spawn(async move {
+        let outgoing_task = tokio::spawn({
+            let pending = Arc::clone(&pending);
+            let publish = publish.clone();
+            async move {
                 let mut stream = writer;
-                while let Some(msg) = msg_recv.recv().await {
+                let mut request_id: i32 = 1;
 
-                    log::info!("executing: {}", msg);
-                    
-                    if msg.starts_with("stop ") || msg == "stop" {
-                        log::warn!("rcon issued stop commad...");
-                        continue
-                    }
+                while let Some(Request { cmd, reply }) = msg_recv.recv().await {
 
-                    let packet = Self::build_packet(
-                        request_id.load(Self::ORDER),
-                        RconMessageType::SERVERDATA_EXECCOMMAND, 
-                        msg
-                    ).unwrap();
-                    request_id.fetch_add(1,Self::ORDER);
-                    stream.write_all(&packet).await.unwrap();
-                    stream.flush().await.unwrap();
-                }
-            });
+                    log::info!("executing: {}", cmd);
+
+                    let id = request_id;
+                    request_id = next_id(request_id);
 
-            let incoming_task = tokio::spawn(async move {
-                let mut stream = reader;
-                loop {
-                    let mut size = [0; 4];
-                    stream.read_exact(&mut size).await.unwrap();
-                    let size = i32::from_le_bytes(size);
+                    pending.lock().unwrap().insert(id, Pending { body: Vec::new(), reply });
 
-                    let mut response = vec![0; size as usize];
-                    stream.read_exact(&mut response).await.unwrap();
-                    // here we have 4bytes PID, 4bytes Type, Payload, 1 byte null terminator
-                    let payload = &response[8..(response.len() - 2)];
+                    // server answers in order, so the reply to an empty response value marks the end of fragments
+                    let mut packets = Self::build_packet(id, RconMessageType::SERVERDATA_EXECCOMMAND, &cmd);
+                    packets.extend(Self::build_packet(id + 1, RconMessageType::SERVERDATA_RESPONSE_VALUE, ""));
 
-                    let response = String::from_utf8(payload.to_vec()).unwrap();
+                    let written = async {
+                        stream.write_all(&packets).await?;
+                        stream.flush().await
+                    }.await;
 
-                    if response.starts_with("Error") {
-                        publish.send(RconOutput::Error(response)).unwrap();
-                    } else {
-                        publish.send(RconOutput::CommandResponse(response)).unwrap();
+                    if let Err(e) = written {
+                        log::error!("cannot write to rcon: {}", e);
+                        if let Some(p) = pending.lock().unwrap().remove(&id) {
+                            let _ = p.reply.send(Err(anyhow!("cannot send command over rcon: {}", e)));
+                        }
+                        let _ = publish.send(RconOutput::Error(e.to_string()));
+                        let _ = publish.send(RconOutput::ConnectionClosed);
+                        break;
                     }
                 }
-            });
+            }
+        });
+
+        let incoming_task = tokio::spawn(async move {
+            let mut stream = reader;
+            loop {
+                let packet = match Self::read_packet(&mut stream).await {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        let eof = e.downcast_ref::<std::io::Error>()
+                            .map(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+                            .unwrap_or(false);
+                        if !eof {
+                            log::error!("cannot read from rcon: {}", e);
+                            let _ = publish.send(RconOutput::Error(e.to_string()));
+                        }
+                        for (_, p) in pending.lock().unwrap().drain() {
+                            let _ = p.reply.send(Err(anyhow!("rcon connection closed before responding")));
+                        }
+                        let _ = publish.send(RconOutput::ConnectionClosed);
+                        break;
+                    }
+                };
 
-            log::info!("rcon connection established: {}", port);
-            Ok(Self {
-                output_receiver: subscribe,
-                command_sender: mpsc_sender,
-                outgoing_task,
-                incoming_task,
-            })
-        } else {
-            anyhow::bail!("Authentication failed {}, {:?}", received_id, response_type);
-        }
+                let mut pending = pending.lock().unwrap();
+
+                if let Some(p) = pending.get_mut(&packet.id) {
+                    p.body.extend_from_slice(&packet.body);
+                    continue;
+                }
+
+                let Some(done) = pending.remove(&packet.id.wrapping_sub(1)) else {
+                    log::warn!("rcon response to unknown request {}", packet.id);
+                    continue;
+                };
+                drop(pending);
+
+                let response = String::from_utf8_lossy(&done.body).into_owned();
+
+                if response.starts_with("Error") {
+                    let _ = publish.send(RconOutput::Error(response.clone()));
+                } else {
+                    let _ = publish.send(RconOutput::CommandResponse(response.clone()));
+                }
+
+                let _ = done.reply.send(Ok(response));
+            }
+        });
+
+        Ok(Self {
+            output_receiver: subscribe,
+            command_sender: mpsc_sender,
+            outgoing_task,
+            incoming_task,
+        })
     }
 
-    pub fn send(&self, cmd: String) -> Result<()> {
-        self.command_sender.try_send(cmd)?;
-        
-        Ok(())
+    /// response to this very command, does not borrow the connection
+    pub fn request(&self, cmd: String) -> impl Future<Output = Result<String>> + 'static {
+        let (reply, response) = oneshot::channel();
+        let sent = self.command_sender.try_send(Request { cmd, reply });
+
+        async move {
+            sent?;
+            response.await.map_err(|_| anyhow!("rcon connection closed before responding"))?
+        }
     }
 
     pub fn output_stream(&self) -> impl Stream<Item = RconOutput> + 'static {
@@ -180,4 +308,98 @@ impl Rcon {
             });
         stream
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    /// takes the auth of the client, answering as vanilla does
+    async fn accept(server: &mut DuplexStream, password: &str) -> Packet {
+        let auth = Rcon::read_packet(server).await.unwrap();
+        assert_eq!(auth.kind, RconMessageType::SERVERDATA_AUTH);
+
+        let id = if auth.body == password.as_bytes() { auth.id } else { -1 };
+        let mut reply = Rcon::build_packet(id, RconMessageType::SERVERDATA_RESPONSE_VALUE, "");
+        reply.extend(Rcon::build_packet(id, RconMessageType::SERVERDATA_AUTH_RESPONSE, ""));
+        server.write_all(&reply).await.unwrap();
+        auth
+    }
+
+    #[actix_web::test]
+    async fn reads_packets() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+
+        client.write_all(&Rcon::build_packet(7, RconMessageType::SERVERDATA_EXECCOMMAND, "list")).await.unwrap();
+        let packet = Rcon::read_packet(&mut server).await.unwrap();
+        assert_eq!(packet.id, 7);
+        assert_eq!(packet.kind, RconMessageType::SERVERDATA_EXECCOMMAND);
+        assert_eq!(packet.body, b"list");
+
+        client.write_all(&(MAX_PACKET + 1).to_le_bytes()).await.unwrap();
+        assert!(Rcon::read_packet(&mut server).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn reassembles_fragments() {
+        let (client, mut server) = tokio::io::duplex(1024);
+
+        let fake = tokio::spawn(async move {
+            accept(&mut server, "secret").await;
+
+            let cmd = Rcon::read_packet(&mut server).await.unwrap();
+            let sentinel = Rcon::read_packet(&mut server).await.unwrap();
+            assert_eq!(cmd.body, b"list");
+            assert_eq!(sentinel.id, cmd.id + 1);
+
+            let mut reply = Rcon::build_packet(cmd.id, RconMessageType::SERVERDATA_RESPONSE_VALUE, "There are 0 ");
+            reply.extend(Rcon::build_packet(cmd.id, RconMessageType::SERVERDATA_RESPONSE_VALUE, "of a max of 20 players online:"));
+            reply.extend(Rcon::build_packet(sentinel.id, RconMessageType::SERVERDATA_RESPONSE_VALUE, "Unknown request 0"));
+            server.write_all(&reply).await.unwrap();
+            server
+        });
+
+        let rcon = Rcon::over(client, "secret".to_owned()).await.unwrap();
+        let response = rcon.request("list".to_owned()).await.unwrap();
+        assert_eq!(response, "There are 0 of a max of 20 players online:");
+
+        drop(fake.await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn rejects_wrong_password() {
+        let (client, mut server) = tokio::io::duplex(1024);
+
+        let fake = tokio::spawn(async move {
+            accept(&mut server, "secret").await;
+            server
+        });
+
+        assert!(Rcon::over(client, "guess".to_owned()).await.is_err());
+        drop(fake.await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn fails_pending_on_close() {
+        let (client, mut server) = tokio::io::duplex(1024);
+
+        tokio::spawn(async move {
+            accept(&mut server, "secret").await;
+            Rcon::read_packet(&mut server).await.unwrap();
+        });
+
+        let rcon = Rcon::over(client, "secret".to_owned()).await.unwrap();
+        let err = rcon.request("stop".to_owned()).await.unwrap_err();
+        assert!(err.to_string().contains("closed"), "{}", err);
+    }
+
+    #[test]
+    fn ids_wrap_before_overflow() {
+        assert_eq!(next_id(1), 3);
+        assert_eq!(next_id(i32::MAX - 4), i32::MAX - 2);
+        assert_eq!(next_id(i32::MAX - 2), 1);
+        // sentinel of the last id still fits
+        assert!((i32::MAX - 2).checked_add(1).is_some());
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fs::OpenOptions, io::Write, os::unix::fs::OpenOptionsExt, path::Path};
+
+/// rcon password of the instance, readable by the manager user only
+pub const SECRET_FILE: &str = "msrvRcon.secret";
+
+/// token that lets anyone download the client pack and nothing else
+pub const PACK_TOKEN_FILE: &str = "msrvPack.secret";
+
+fn generate() -> String {
+    // v4 uuids come from the os rng, two of them are plenty
+    let a = uuid::Uuid::new_v4();
+    let b = uuid::Uuid::new_v4();
+    format!("{}{}", hex::encode(a.as_bytes()), hex::encode(b.as_bytes()))
+}
+
+/// written aside with 0600 and renamed over, so a half written secret is never read
+fn write(at: &Path, file: &str, secret: &str) -> anyhow::Result<()> {
+    let tmp = at.join(format!("{}.tmp", file));
+    let _ = std::fs::remove_file(&tmp);
+
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    out.write_all(secret.as_bytes())?;
+    out.sync_all()?;
+    drop(out);
+
+    std::fs::rename(tmp, at.join(file))?;
+    Ok(())
+}
+
+fn read_or_generate(at: &Path, file: &str) -> anyhow::Result<String> {
+    match std::fs::read_to_string(at.join(file)) {
+        Ok(secret) if !secret.trim().is_empty() => Ok(secret.trim().to_owned()),
+        Ok(_) => regenerate(at, file),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => regenerate(at, file),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn regenerate(at: &Path, file: &str) -> anyhow::Result<String> {
+    let secret = generate();
+    write(at, file, &secret)?;
+    Ok(secret)
+}
+
+/// generated on first use
+pub fn rcon_password(at: &Path) -> anyhow::Result<String> {
+    read_or_generate(at, SECRET_FILE)
+}
+
+/// replaces the secret, the server learns it from `server.properties` on its next start
+pub fn rotate(at: &Path) -> anyhow::Result<String> {
+    regenerate(at, SECRET_FILE)
+}
+
+/// generated on first use
+pub fn pack_token(at: &Path) -> anyhow::Result<String> {
+    read_or_generate(at, PACK_TOKEN_FILE)
+}
+
+/// links handed out before stop working
+pub fn rotate_pack_token(at: &Path) -> anyhow::Result<String> {
+    regenerate(at, PACK_TOKEN_FILE)
+}
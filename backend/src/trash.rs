@@ -0,0 +1,153 @@
+use std::{path::{Path, PathBuf}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use anyhow::anyhow;
+
+use crate::model;
+
+/// where deleted servers wait for their retention to pass, inside the data folder
+pub const TRASH_DIR: &str = ".trash";
+
+/// entries being removed are renamed with this first, so they are never listed or purged twice
+const PURGING_PREFIX: &str = ".purging-";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// ids look like `<instance id>-<unix seconds of deletion>`
+fn parse_id(id: &str) -> Option<(&str, u64)> {
+    let (dir, ts) = id.rsplit_once('-')?;
+    Some((dir, ts.parse().ok()?))
+}
+
+/// server name as written in the manifest
+fn read_name(dir: &Path) -> Option<String> {
+    let raw = std::fs::read_to_string(dir.join(crate::instance::MANIFEST_NAME)).ok()?;
+    let val: serde_json::Value = serde_json::from_str(&raw).ok()?;
+    val.get("name")?.as_str().map(|n| n.to_owned())
+}
+
+fn entry_path(trash: &Path, id: &str) -> anyhow::Result<PathBuf> {
+    if parse_id(id).is_none() || id.contains('/') || id.starts_with('.') {
+        return Err(anyhow!("bad trash id: {}", id));
+    }
+
+    let path = trash.join(id);
+    if !path.is_dir() {
+        return Err(anyhow!("no such trash entry: {}", id));
+    }
+
+    Ok(path)
+}
+
+/// moves server directory into the trash
+pub fn move_in(trash: &Path, at: &Path, retention: Duration) -> anyhow::Result<model::TrashEntry> {
+    std::fs::create_dir_all(trash)?;
+
+    let dir = at.file_name().ok_or(anyhow!("path has no name"))?.to_string_lossy().into_owned();
+    let name = read_name(at).unwrap_or_else(|| dir.clone());
+
+    let deleted_at = now();
+    let id = format!("{}-{}", dir, deleted_at);
+
+    std::fs::rename(at, trash.join(&id))?;
+
+    Ok(model::TrashEntry {
+        id,
+        name,
+        deleted_at,
+        expires_at: deleted_at + retention.as_secs(),
+    })
+}
+
+/// moves entry back into `servers_dir` under its old directory name
+pub fn move_out(trash: &Path, id: &str, servers_dir: &Path) -> anyhow::Result<PathBuf> {
+    let from = entry_path(trash, id)?;
+    let (dir, _) = parse_id(id).ok_or(anyhow!("bad trash id: {}", id))?;
+
+    let to = servers_dir.join(dir);
+    if to.exists() {
+        return Err(anyhow!("{:?} already exists", &to));
+    }
+
+    std::fs::rename(from, &to)?;
+    Ok(to)
+}
+
+pub fn list(trash: &Path, retention: Duration) -> Vec<model::TrashEntry> {
+    let Ok(entries) = std::fs::read_dir(trash) else {
+        return Vec::new();
+    };
+
+    let mut list: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| {
+            let id = e.file_name().to_string_lossy().into_owned();
+            if id.starts_with('.') {
+                return None;
+            }
+            let (dir, deleted_at) = parse_id(&id)?;
+            Some(model::TrashEntry {
+                name: read_name(&e.path()).unwrap_or_else(|| dir.to_owned()),
+                deleted_at,
+                expires_at: deleted_at + retention.as_secs(),
+                id,
+            })
+        })
+        .collect();
+
+    list.sort_by_key(|e| e.deleted_at);
+    list
+}
+
+/// removal happens on a separate thread, worlds can be big
+pub fn purge(trash: &Path, id: &str) -> anyhow::Result<()> {
+    let doomed = doom(trash, id)?;
+
+    std::thread::spawn(move || {
+        if let Err(e) = std::fs::remove_dir_all(&doomed) {
+            log::error!("couldn't remove {:?}: {}", &doomed, e);
+        }
+    });
+
+    Ok(())
+}
+
+/// renamed first, so a removal cut short is finished by `clean_leftovers`
+fn doom(trash: &Path, id: &str) -> anyhow::Result<PathBuf> {
+    let from = entry_path(trash, id)?;
+    let doomed = trash.join(format!("{}{}", PURGING_PREFIX, id));
+
+    std::fs::rename(from, &doomed)?;
+    Ok(doomed)
+}
+
+/// purges everything past retention, this blocks thread
+pub fn purge_expired(trash: &Path, retention: Duration) {
+    let now = now();
+
+    for entry in list(trash, retention).into_iter().filter(|e| e.expires_at <= now) {
+        log::info!("trash entry {} expired", &entry.id);
+        let res = doom(trash, &entry.id).and_then(|doomed| Ok(std::fs::remove_dir_all(doomed)?));
+        if let Err(e) = res {
+            log::error!("couldn't purge {}: {}", &entry.id, e);
+        }
+    }
+}
+
+/// leftovers of purges interrupted by a restart, this blocks thread
+pub fn clean_leftovers(trash: &Path) {
+    let Ok(entries) = std::fs::read_dir(trash) else {
+        return;
+    };
+
+    for e in entries.filter_map(|e| e.ok()) {
+        if e.file_name().to_string_lossy().starts_with(PURGING_PREFIX) {
+            let _ = std::fs::remove_dir_all(e.path());
+        }
+    }
+}
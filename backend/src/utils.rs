@@ -4,7 +4,7 @@ use zip::ZipArchive;
 
 use crate::*;
 
-use std::{ffi::OsString, fs::{File, Permissions}, ops::Range, path::Path, process::Command};
+use std::{ffi::OsString, fs::{File, Permissions}, io::{Read, Seek}, ops::Range, path::Path, process::Command};
 
 #[derive(Debug)]
 pub struct Indices(Range<u16>, bit_set::BitSet);
@@ -18,7 +18,8 @@ impl Indices {
         self.0.clone()
     }
 
-    pub fn try_take(&mut self, idx: u16) -> Result<(), anyhow::Error> {
+    /// whether `idx` could be taken right now
+    pub fn check(&self, idx: u16) -> Result<(), anyhow::Error> {
         if !self.0.contains(&idx) {
             return Err(anyhow!("out of bounds"));
         };
@@ -27,11 +28,39 @@ impl Indices {
             return Err(anyhow!("already occupied"));
         };
 
+        Ok(())
+    }
+
+    pub fn try_take(&mut self, idx: u16) -> Result<(), anyhow::Error> {
+        self.check(idx)?;
+
+        self.1.insert(idx.into());
+
+        Ok(())
+    }
+
+    /// like `try_take`, but also refuses ports something else on the host listens on
+    pub fn take_available(&mut self, idx: u16) -> Result<(), anyhow::Error> {
+        self.check(idx)?;
+
+        if !port_available(idx) {
+            return Err(anyhow!("port {} is used by another process", idx));
+        }
+
         self.1.insert(idx.into());
 
         Ok(())
     }
 
+    /// takes the lowest port free both for us and for the host
+    pub fn allocate(&mut self) -> Option<u16> {
+        let idx = self.0.clone().find(|i| !self.1.contains((*i).into()) && port_available(*i))?;
+
+        self.1.insert(idx.into());
+
+        Some(idx)
+    }
+
     pub fn free(&mut self, idx: u16) -> anyhow::Result<()> {
         if !self.0.contains(&idx) {
             return Err(anyhow!("out of bounds"));
@@ -54,6 +83,67 @@ impl Indices {
     }
 }
 
+/// nothing on the host holds the port, over both TCP and UDP (query)
+pub fn port_available(port: u16) -> bool {
+    std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
+        && std::net::UdpSocket::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// names of extra ports, like `query` or `voice-chat`
+pub fn validate_port_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name.len() > 32 || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-') {
+        return Err(anyhow!("port name must be 1 to 32 lowercase latin letters, digits, '_' or '-'"));
+    }
+    Ok(())
+}
+
+/// dns name, lowercase
+pub fn validate_hostname(host: &str) -> anyhow::Result<()> {
+    let valid_label = |l: &str| {
+        !l.is_empty() && l.len() <= 63
+            && !l.starts_with('-') && !l.ends_with('-')
+            && l.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    };
+
+    if host.len() > 253 || !host.split('.').all(valid_label) {
+        return Err(anyhow!("{:?} is not a valid hostname", host));
+    }
+    Ok(())
+}
+
+pub const MAX_NAME_LEN: usize = 64;
+
+fn name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// latin letters, digits, `_`, `-` and `.`, starting with a letter or a digit
+pub fn validate_server_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(anyhow!("name must be 1 to {} characters long", MAX_NAME_LEN));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) || !name.chars().all(name_char) {
+        return Err(anyhow!("name may only contain latin letters, digits, '_', '-' and '.', and start with a letter or a digit"));
+    }
+    Ok(())
+}
+
+/// closest valid name, for names from before validation
+pub fn sanitize_server_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if name_char(c) { c } else { '_' })
+        .skip_while(|c| !c.is_ascii_alphanumeric())
+        .take(MAX_NAME_LEN)
+        .collect();
+
+    if name.is_empty() {
+        "server".to_owned()
+    } else {
+        name
+    }
+}
+
 /// it only disposed process, not kills its
 pub fn dispose(mut child: std::process::Child) {
     std::thread::spawn(move || {
@@ -69,24 +159,13 @@ pub fn dispose(mut child: std::process::Child) {
     });
 }
 
-/// make sure that dir exists and has manifest file
+/// make sure that dir exists, manifest is to be written by the caller
 pub fn initialize_server_directory<P: AsRef<Path>,R>(at: P,act: impl FnOnce() -> anyhow::Result<R>) -> anyhow::Result<R> {
     log::info!("preparing dir for server at {:?}",at.as_ref());
 
     std::fs::create_dir(at.as_ref()).unwrap();
 
-    let r = act()?;
-
-    std::fs::File::create_new(at.as_ref().join(instance::MANIFEST_NAME))?;
-
-    Ok(r)
-}
-
-pub fn open_manifest<P: AsRef<Path>>(at: P) -> Result<File, std::io::Error> {
-    File::options()
-        .write(true)    
-        .read(true)
-        .open(at.as_ref().join(instance::MANIFEST_NAME))
+    act()
 }
 
 pub fn generate_classpath<P: AsRef<Path>>(at: P) -> anyhow::Result<OsString> {
@@ -126,14 +205,26 @@ pub fn unpack_at(at: impl AsRef<Path>, data: &mut UploadValue) -> anyhow::Result
 
     let mut archive = ZipArchive::new(&mut data.content)?;
 
+    unpack_archive_at(at, &mut archive, None)
+}
+
+/// unpacks entries of archive, if prefix is given only entries under it are unpacked with prefix stripped
+/// this blocks thread
+pub fn unpack_archive_at<R: Read + Seek>(at: impl AsRef<Path>, archive: &mut ZipArchive<R>, prefix: Option<&str>) -> anyhow::Result<()> {
+
     log::info!("starting to unpack at {:?}", at.as_ref());
 
     for i in 0..archive.len() {
         let mut archive_file = archive.by_index(i)?;
 
-        let outpath = match archive_file.enclosed_name() {
-            Some(path) => at.as_ref().join(path),
-            None => continue,
+        let outpath = match (archive_file.enclosed_name(), prefix) {
+            (Some(path), None) => at.as_ref().join(path),
+            (Some(path), Some(prefix)) => match path.strip_prefix(prefix) {
+                Ok(rest) if rest.as_os_str().is_empty() => continue,
+                Ok(rest) => at.as_ref().join(rest),
+                Err(_) => continue,
+            },
+            (None, _) => continue,
         };
 
         // Create directories if necessary
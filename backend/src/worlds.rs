@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use async_graphql::UploadValue;
+use zip::ZipArchive;
+
+use crate::{instance, model, properties::ServerProperties, utils};
+
+pub const DEFAULT_LEVEL_NAME: &str = "world";
+
+const LEVEL_DAT: &str = "level.dat";
+
+/// dimensions bukkit keeps next to the main world
+const BUKKIT_SUFFIXES: [&str; 2] = ["_nether", "_the_end"];
+
+pub fn validate_name(name: &str) -> anyhow::Result<()> {
+    let ok = !name.is_empty()
+        && name.len() <= 64
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+
+    if ok {
+        Ok(())
+    } else {
+        Err(anyhow!("world name must be 1-64 of [A-Za-z0-9_.-] and not start with '.'"))
+    }
+}
+
+fn properties_path(at: &Path) -> PathBuf {
+    at.join(instance::SERVER_PROPERTIES_FILE)
+}
+
+pub fn active(at: impl AsRef<Path>) -> anyhow::Result<String> {
+    let props = ServerProperties::load(properties_path(at.as_ref()))?;
+    Ok(props
+        .get("level-name")
+        .filter(|n| !n.is_empty())
+        .unwrap_or(DEFAULT_LEVEL_NAME)
+        .to_owned())
+}
+
+/// directories of the server holding a `level.dat`
+/// this blocks thread
+pub fn list(at: impl AsRef<Path>) -> anyhow::Result<Vec<model::WorldInfo>> {
+    let at = at.as_ref();
+    let active = active(at)?;
+
+    let mut names: Vec<String> = std::fs::read_dir(at)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().join(LEVEL_DAT).is_file())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+
+    let all = names.clone();
+    names.retain(|n| {
+        !BUKKIT_SUFFIXES
+            .iter()
+            .any(|s| n.strip_suffix(s).map(|base| all.iter().any(|a| a == base)).unwrap_or(false))
+    });
+    names.sort();
+
+    Ok(names
+        .into_iter()
+        .map(|name| model::WorldInfo {
+            active: name == active,
+            name,
+        })
+        .collect())
+}
+
+/// `level-name` comes from a file anyone with access to the server can edit, it must stay a single directory of it
+fn level_dir(at: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let mut parts = Path::new(name).components();
+    match (parts.next(), parts.next()) {
+        (Some(std::path::Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(at.join(name)),
+        _ => Err(anyhow!("level-name {:?} is not a directory of the server", name)),
+    }
+}
+
+fn world_dir(at: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    validate_name(name)?;
+    Ok(at.join(name))
+}
+
+/// finds the directory with `level.dat` in an unpacked archive
+fn find_level(dir: &Path, depth: usize) -> Option<PathBuf> {
+    if dir.join(LEVEL_DAT).is_file() {
+        return Some(dir.to_owned());
+    }
+    if depth == 0 {
+        return None;
+    }
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .find_map(|e| find_level(&e.path(), depth - 1))
+}
+
+/// this blocks thread
+pub fn upload(at: impl AsRef<Path>, name: &str, data: &mut UploadValue) -> anyhow::Result<()> {
+    let at = at.as_ref();
+    let target = world_dir(at, name)?;
+
+    if target.exists() {
+        return Err(anyhow!("world {} already exists", name));
+    }
+
+    let staging = at.join(format!(".{}.uploading", name));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir(&staging)?;
+
+    let res = (|| {
+        let mut archive = ZipArchive::new(&mut data.content)?;
+        utils::unpack_archive_at(&staging, &mut archive, None)?;
+
+        let level = find_level(&staging, 2).ok_or_else(|| anyhow!("archive has no {}", LEVEL_DAT))?;
+        std::fs::rename(level, &target)?;
+        Ok(())
+    })();
+
+    let _ = std::fs::remove_dir_all(&staging);
+    res
+}
+
+/// this blocks thread
+pub fn switch(at: impl AsRef<Path>, name: &str) -> anyhow::Result<()> {
+    let at = at.as_ref();
+    let dir = world_dir(at, name)?;
+
+    if !dir.join(LEVEL_DAT).is_file() {
+        return Err(anyhow!("no world {}", name));
+    }
+
+    let mut props = ServerProperties::load(properties_path(at))?;
+    props.set("level-name", name);
+    props.save(properties_path(at))
+}
+
+/// this blocks thread
+pub fn delete(at: impl AsRef<Path>, name: &str) -> anyhow::Result<()> {
+    let at = at.as_ref();
+    let dir = world_dir(at, name)?;
+
+    if active(at)? == name {
+        return Err(anyhow!("cannot delete active world {}", name));
+    }
+
+    if !dir.join(LEVEL_DAT).is_file() {
+        return Err(anyhow!("no world {}", name));
+    }
+
+    for suffix in BUKKIT_SUFFIXES {
+        let _ = std::fs::remove_dir_all(at.join(format!("{}{}", name, suffix)));
+    }
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+/// moves active world aside as a backup, server generates a new one on next start
+/// returns name of the backup
+/// this blocks thread
+pub fn reset(at: impl AsRef<Path>, seed: Option<String>, level_type: Option<String>) -> anyhow::Result<Option<String>> {
+    let at = at.as_ref();
+    let active = active(at)?;
+    let level = level_dir(at, &active)?;
+
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let backup = format!("{}-backup-{}", active, ts);
+
+    let moved = if level.exists() {
+        std::fs::rename(&level, at.join(&backup))?;
+        for suffix in BUKKIT_SUFFIXES {
+            let dim = at.join(format!("{}{}", active, suffix));
+            if dim.exists() {
+                std::fs::rename(dim, at.join(format!("{}{}", backup, suffix)))?;
+            }
+        }
+        Some(backup)
+    } else {
+        None
+    };
+
+    let mut props = ServerProperties::load(properties_path(at))?;
+    props.set("level-seed", seed.unwrap_or_default());
+    if let Some(level_type) = level_type {
+        props.set("level-type", level_type);
+    }
+    props.save(properties_path(at))?;
+
+    Ok(moved)
+}
@@ -13,7 +13,10 @@ import { useMemo } from "react";
 type FormData = {
     maxMemory: {value: number, displayValue: string};
     port: {value: number, displayValue: string};
+    rcon: {value: number, displayValue: string};
+    diskQuota: {value: number, displayValue: string};
     javaArgs: string;
+    hostnames: string;
 };
 
 type PageProps = {
@@ -22,10 +25,11 @@ type PageProps = {
 
 const Alter = ({pageData}: SSRProps<PageProps>) => {
 
-    const { data: ports } = useQuery<{ portsTaken: {portLimits: [number,number]} }>(gql`
+    const { data: ports } = useQuery<{ portsTaken: {portLimits: [number,number], rconLimits: [number,number]} }>(gql`
         {
             portsTaken {
                 portLimits,
+                rconLimits
             }
         }
     `);
@@ -48,17 +52,21 @@ const Alter = ({pageData}: SSRProps<PageProps>) => {
     console.log("instanceData",instanceData);
 
     const portLimits = ports?.portsTaken.portLimits ?? [1024,65535];
+    const rconLimits = ports?.portsTaken.rconLimits ?? [1024,65535];
 
     const [alter,{ error: errorM }] = useMutation<{alterServer: boolean}>(gql`
-        mutation AlterServer($name: String!, $maxMemory: Float, $upCmd: String, $port: Int, $password: String!) {
-            alterServer(name: $name, maxMemory: $maxMemory, upCmd: $upCmd, port: $port, password: $password)
+        mutation AlterServer($name: String!, $maxMemory: Float, $upCmd: String, $port: Int, $rcon: Int, $diskQuota: Float, $hostnames: [String!], $password: String!) {
+            alterServer(name: $name, maxMemory: $maxMemory, upCmd: $upCmd, port: $port, rcon: $rcon, diskQuota: $diskQuota, hostnames: $hostnames, password: $password)
         }
     `);
 
     const mutate = async (
         rest: {
             maxMemory: number | null,
-            port: number | null
+            port: number | null,
+            rcon: number | null,
+            diskQuota: number | null,
+            hostnames: string[] | null
         },
         password: string
     ) => {
@@ -77,11 +85,14 @@ const Alter = ({pageData}: SSRProps<PageProps>) => {
             properties: {
                 maxMemory: NumberInputData(1,32),
                 javaArgs: { type: ["string", "null"] },
-                port: NumberInputData(portLimits[0],portLimits[1])
+                hostnames: { type: ["string", "null"] },
+                port: NumberInputData(portLimits[0],portLimits[1]),
+                rcon: NumberInputData(rconLimits[0],rconLimits[1]),
+                diskQuota: NumberInputData(0,100000)
             },
             additionalProperties: false
         }
-    },[portLimits]);
+    },[portLimits, rconLimits]);
 
     const {
         handleSubmit,
@@ -93,8 +104,11 @@ const Alter = ({pageData}: SSRProps<PageProps>) => {
         }),
         defaultValues: {
             maxMemory: {value: instanceData?.max_memory ?? 1, displayValue: ""},
-            port: {value: instanceData?.port ?? 1, displayValue: ""},
-            javaArgs: instanceData?.java_args?.join(" ") ?? ""
+            port: {value: instanceData?.ports?.port ?? instanceData?.port ?? 1, displayValue: ""},
+            rcon: {value: instanceData?.ports?.rcon ?? 1, displayValue: ""},
+            diskQuota: {value: instanceData?.disk_quota ?? 0, displayValue: ""},
+            javaArgs: instanceData?.java_args?.join(" ") ?? "",
+            hostnames: instanceData?.hostnames?.join(" ") ?? ""
         }
     });
 
@@ -109,7 +123,10 @@ const Alter = ({pageData}: SSRProps<PageProps>) => {
         let data = {
             maxMemory: fd.maxMemory.value,
             port: fd.port.value,
-            javaArgs: fd.javaArgs === "" ? null : fd.javaArgs
+            rcon: fd.rcon.displayValue === "" ? null : fd.rcon.value,
+            diskQuota: fd.diskQuota.displayValue === "" ? null : fd.diskQuota.value,
+            javaArgs: fd.javaArgs === "" ? null : fd.javaArgs,
+            hostnames: fd.hostnames.split(/\s+/).filter((h) => h !== "")
         };
         
         let res = await mutate(data, password);
@@ -139,6 +156,18 @@ const Alter = ({pageData}: SSRProps<PageProps>) => {
             <Label>Port, <DisplayRange range={portLimits}/></Label><br />
             <NumberInput type="int" name="port" control={control} placeholder={instanceData?.port?.toString() ?? "-"} /><br />
             {errors.port && <ErrorP>{errors.port.message}</ErrorP>}
+
+            <Label>Rcon port, <DisplayRange range={rconLimits}/></Label><br />
+            <NumberInput type="int" name="rcon" control={control} placeholder={instanceData?.ports?.rcon?.toString() ?? "-"} /><br />
+            {errors.rcon && <ErrorP>{errors.rcon.message}</ErrorP>}
+
+            <Label>Disk quota, in GB, 0 for none</Label><br />
+            <NumberInput type="float" name="diskQuota" control={control} placeholder={instanceData?.disk_quota?.toString() ?? "none"} /><br />
+            {errors.diskQuota && <ErrorP>{errors.diskQuota.message}</ErrorP>}
+
+            <Label>Hostnames the proxy routes here, space separated</Label><br />
+            <TextArea name="hostnames" control={control} placeholder={instanceData?.hostnames?.join(" ") || "none"} /><br />
+            {errors.hostnames && <ErrorP>{errors.hostnames.message}</ErrorP>}
         </form>
     );
 }
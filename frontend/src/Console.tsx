@@ -0,0 +1,101 @@
+import { makeOnLoad, SSRProps } from "./lib";
+import styled from 'styled-components';
+import { KeyboardEvent, useEffect, useRef, useState } from "react";
+import { HomeLink, Label, SInput, TextBig } from "./components/UIComps";
+import Btn from "./components/Button";
+
+const Form = styled.div`
+    display: flex;
+    flex-direction: row;
+    align-items: stretch;
+    gap: 1rem;
+    height: 12vh;
+    margin: 0 auto;
+`;
+
+const VStack = styled.span`
+    height: 4.5rem;
+    display: inline-flex;
+    align-items: start;
+    justify-content: stretch;
+    flex-direction: column;
+`;
+
+const OutputPre = styled.pre`
+    padding: 1rem;
+    margin-top: 1rem;
+    background-color: #f8f9fa;
+    height: calc(100vh - 12vh - 6rem);
+    overflow-y: scroll;
+    border: 1px solid #db9f30;
+    border-radius: 4px;
+`;
+
+// lines kept on the page
+const WINDOW_SIZE = 500;
+
+type Props = {
+    name: string
+}
+
+const Console = ({ pageData }: SSRProps<Props>) => {
+
+    let [password] = useState(() => prompt("Please enter the password to use the console of this server"));
+
+    if (!password) {
+        window.location.href = '/';
+        return;
+    }
+
+    const [lines,setLines] = useState<string[]>([]);
+    const [line,setLine] = useState<string>("");
+    const socket = useRef<WebSocket | null>(null);
+
+    useEffect(() => {
+        const proto = window.location.protocol === "https:" ? "wss:" : "ws:";
+        const ws = new WebSocket(`${proto}//${window.location.host}/console_ws?name=${encodeURIComponent(pageData.name)}`);
+
+        // first frame authorizes the session
+        ws.onopen = () => ws.send(password);
+        ws.onmessage = (ev) => setLines((old) => [...old, ev.data].slice(-WINDOW_SIZE));
+        ws.onclose = (ev) => {
+            if (ev.reason) {
+                alert(ev.reason);
+            }
+            window.location.href = '/';
+        };
+
+        socket.current = ws;
+        return () => ws.close();
+    }, [pageData.name]);
+
+    const send = () => {
+        socket.current?.send(line);
+        setLine("");
+    };
+
+    const onKeyDown = (ev: KeyboardEvent<HTMLInputElement>) => {
+        if (ev.key === "Enter") {
+            send();
+        }
+    };
+
+    return (
+        <div>
+            <Form>
+                <VStack>
+                    <HomeLink href="/">Home</HomeLink>
+                    <TextBig>Console of {pageData.name} server: </TextBig>
+                </VStack>
+                <VStack>
+                    <Label>Input:</Label>
+                    <SInput type="text" value={line} onChange={(ev) => setLine(ev.target.value)} onKeyDown={onKeyDown} />
+                </VStack>
+                <VStack><Btn onClick={send}>Send</Btn></VStack>
+            </Form>
+            <OutputPre>{lines.join("\n")}</OutputPre>
+        </div>
+    );
+}
+
+window.onload = makeOnLoad(Console);
@@ -19,8 +19,8 @@ type ServerData = {
     maxMemory: number,
 
     ports: {
-        port: number,
-        rcon: number
+        port: number | null,
+        rcon: number | null
     },
 }
 
@@ -90,7 +90,7 @@ const CreatePage = ({}: SSRProps) => {
                 }
               },
             },
-            required: ["name", "javaArgs", "url", "maxMemory", "port", "rcon", "instanceUpload"]
+            required: ["name", "javaArgs", "url", "maxMemory", "instanceUpload"]
           }
     },[ports]);
 
@@ -206,19 +206,19 @@ const CreatePage = ({}: SSRProps) => {
             {errors.setupCmd && <ErrorP>{errors.setupCmd.message}</ErrorP>} */}
 
             <Label>Url to client modpack</Label><br />
-            <SInput type="text" {...register("url")} placeholder="url to mod list" /><br />
+            <SInput type="text" {...register("url")} placeholder="url to mod list, /client_pack?name=<name>&token=<clientPackToken> serves one built from the server" /><br />
             {errors.url && <ErrorP>{errors.url.message}</ErrorP>}
 
             <Label>Maximum memory, in GB <DisplayRange range={memoryLimits}/> </Label><br />
             <NumberInput name="maxMemory" type="float" control={control} placeholder="max allowed memory consumption" /><br />
 
             <Label>Port, {ports?.portsTaken.portLimits ? <DisplayRange range={ports.portsTaken.portLimits}/> : null}</Label><br />
-            <NumberInput name="port" type="int" control={control} placeholder="server port" /><br />
+            <NumberInput name="port" type="int" control={control} placeholder="server port, free one is picked when empty" /><br />
 
             <Label>Rcon, {ports?.portsTaken.rconLimits ? <DisplayRange range={ports.portsTaken.rconLimits}/> : null}</Label><br />
-            <NumberInput name="rcon" type="int" control={control} placeholder="server rcon" /><br />
+            <NumberInput name="rcon" type="int" control={control} placeholder="server rcon, free one is picked when empty" /><br />
 
-            <Label>Archive with server instance, no way to limit size right now</Label><br /> 
+            <Label>Archive with server instance, Modrinth .mrpack or CurseForge pack zip, no way to limit size right now</Label><br /> 
             {(uploading)? <TextBig>Uploading...</TextBig> : null}
             <SInput type="file" onChange={onChange} /><br /> 
             {errors.instanceUpload && <ErrorP>{errors.instanceUpload.message}</ErrorP>} 
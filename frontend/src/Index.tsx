@@ -1,6 +1,6 @@
 import { useState } from "react";
 import { makeOnLoad, SSRProps } from "./lib";
-import { InstanceDescriptor } from "./model";
+import { DiskUsage, InstanceDescriptor } from "./model";
 import InstanceDisplay from "./components/InstanceDesc";
 import InstanceActions from "./components/InstanceActions";
 import Btn from "./components/Button";
@@ -31,7 +31,8 @@ const ListWrapper = styled.div`
 type ServerData = {
     servers: Record<string,{
         data: InstanceDescriptor,
-        state: string
+        state: string,
+        disk: DiskUsage | null
     }>
 }
 
@@ -78,16 +79,18 @@ const Index = ({}: SSRProps) => {
                 {
                     Object.entries((data?.servers ?? {}))
                         .sort(([a], [b]) => a.localeCompare(b))
-                        .map(([name,{data,state}]) => {
+                        .map(([name,{data,state,disk}]) => {
                             switch (state) {
                                 case "Stopped":
                                 case "Running":
+                                case "RconUnavailable":
                                 case "Crashed":
                                     return (
                                         <InstanceDisplay
                                             key={name}
                                             instance={data}
                                             state={state}
+                                            disk={disk}
                                             selected={selected === name}
                                             setSelected={
                                                 (selected === name)
@@ -1,7 +1,7 @@
 import { makeOnLoad, SSRProps } from "./lib";
 import styled from 'styled-components';
-import { gql, useMutation, useSubscription } from '@apollo/client';
-import { useState } from "react";
+import { gql, useMutation, useQuery, useSubscription } from '@apollo/client';
+import { KeyboardEvent, useState } from "react";
 import { HomeLink, Label, SInput, TextBig } from "./components/UIComps";
 import Btn from "./components/Button";
 
@@ -55,6 +55,21 @@ const Rcon = ({ pageData }: SSRProps<Props>) => {
         }
     `);
 
+    // previous commands, newest first
+    const { data: historyData } = useQuery(gql`
+        query History($name: String!,$password: String!) {
+            commandHistory(name: $name,limit: 50,password: $password) {
+                command
+            }
+        }
+    `, {
+        variables: { name: pageData.name, password }
+    });
+
+    const [sent,setSent] = useState<string[]>([]);
+    // position in the recalled commands, -1 is the one being typed
+    const [recall,setRecall] = useState(-1);
+
     const { error, data } = useSubscription(
         gql`
             subscription Subscription($name: String!) {
@@ -78,6 +93,35 @@ const Rcon = ({ pageData }: SSRProps<Props>) => {
     // for sanity this must only be primitive string or null
     let rconOutput: string[] = data?.rconOutput ?? [];
 
+    const recalled: string[] = [
+        ...sent,
+        ...(historyData?.commandHistory ?? []).map((r: { command: string }) => r.command)
+    ];
+
+    const send = () => {
+        msg({ variables: { name: pageData.name, message, password } });
+        setSent([message, ...sent]);
+        setRecall(-1);
+        setMsg("");
+    };
+
+    const onKeyDown = (ev: KeyboardEvent<HTMLInputElement>) => {
+        let next = recall;
+        if (ev.key === "ArrowUp") {
+            next = Math.min(recall + 1, recalled.length - 1);
+        } else if (ev.key === "ArrowDown") {
+            next = Math.max(recall - 1, -1);
+        } else if (ev.key === "Enter") {
+            send();
+            return;
+        } else {
+            return;
+        }
+        ev.preventDefault();
+        setRecall(next);
+        setMsg(next === -1 ? "" : recalled[next]);
+    };
+
     return (
         <div>
             <Form>
@@ -87,9 +131,9 @@ const Rcon = ({ pageData }: SSRProps<Props>) => {
                 </VStack>
                 <VStack>
                     <Label>Command:</Label>
-                    <SInput type="text" value={message} onChange={(ev) => setMsg(ev.target.value)} />
+                    <SInput type="text" value={message} onChange={(ev) => setMsg(ev.target.value)} onKeyDown={onKeyDown} />
                 </VStack>
-                <VStack><Btn onClick={() => { msg({ variables: { name: pageData.name, message, password } }); }}>Send</Btn></VStack>
+                <VStack><Btn onClick={send}>Send</Btn></VStack>
             </Form>
             <OutputContainer>
                 <TextBig>Rcon Output:</TextBig>
@@ -19,8 +19,8 @@ type ServerData = {
     maxMemory: number,
 
     ports: {
-        port: number,
-        rcon: number
+        port: number | null,
+        rcon: number | null
     },
 }
 
@@ -34,9 +34,19 @@ type FormData = Omit<ServerData, SpecialHandling> & {
     instanceUpload: {value: FileList, formData: File[]} | null
 };
 
+type RepairProposal = {
+  url: string | null,
+  java_args: string[],
+  max_memory: number | null,
+  port: number | null,
+  rcon: number | null,
+  problems: string[]
+}
+
 type PageProps = {
   name: string,
-  data: any
+  data: any,
+  proposal: RepairProposal | null
 }
 
 const ReNewPage = ({ pageData }: SSRProps<PageProps>) => {
@@ -72,7 +82,7 @@ const ReNewPage = ({ pageData }: SSRProps<PageProps>) => {
               port: NumberInputData(ports?.portsTaken.portLimits[0] ?? 1, ports?.portsTaken.portLimits[1] ?? 65535),
               rcon: NumberInputData(ports?.portsTaken.rconLimits[0] ?? 1, ports?.portsTaken.rconLimits[1] ?? 65535),
             },
-            required: ["javaArgs", "url", "maxMemory", "port", "rcon"]
+            required: ["javaArgs", "url", "maxMemory"]
           }
     },[ports]);
 
@@ -88,11 +98,34 @@ const ReNewPage = ({ pageData }: SSRProps<PageProps>) => {
       defaultValues: {
         // serverJar: "",
         // setupCmd: null,
-        url: "",
+        url: pageData.proposal?.url ?? "",
+        javaArgs: pageData.proposal?.java_args.join(" ") ?? "",
         instanceUpload: null
       }
     });
 
+    const [repairServer, {error: repairError}] = useMutation<{repairServer: boolean}>(gql`
+        mutation Repair($name: String!, $password: String!) {
+            repairServer(name: $name, password: $password)
+        }
+    `);
+
+    const proposal = pageData.proposal;
+
+    const onRepair = async () => {
+        let password = prompt("Please enter the password to repair the server");
+
+        if (!password) {
+          return;
+        }
+
+        const result = await repairServer({ variables: { name: pageData.name, password } });
+
+        if (result.data?.repairServer) {
+          window.location.href = '/';
+        }
+    };
+
     const [renewServer, {error}] = useMutation<any>(gql`
         mutation Mutation($name: String!, $data: NewServer!,$upload: Upload!, $password: String!) {
             reNewServer(name: $name,data: $data, password: $password)
@@ -143,6 +176,13 @@ const ReNewPage = ({ pageData }: SSRProps<PageProps>) => {
             <p><HomeLink href="/">Home</HomeLink><TextBig>Renew server page: </TextBig><Btn type="submit" disabled={pLoading} >Renew server</Btn></p>
             {error && <ErrorP>{error.message}</ErrorP>}
 
+            {proposal && <div>
+              <Label>Proposed: port {proposal.port ?? "?"}, rcon {proposal.rcon ?? "?"}, memory {proposal.max_memory ?? "?"} GB, url {proposal.url ?? "?"}</Label><br />
+              {proposal.problems.map((p) => <ErrorP key={p}>{p}</ErrorP>)}
+              <Btn type="button" disabled={proposal.problems.length > 0} onClick={onRepair}>Confirm repair</Btn>
+              {repairError && <ErrorP>{repairError.message}</ErrorP>}
+            </div>}
+
             {/* <Label>Path to jar in archive to be executed as a server</Label><br />
             <SInput type="text" {...register("serverJar")} placeholder="relative path required, aka ./_.jar" /><br />
             {errors.serverJar && <ErrorP>{errors.serverJar.message}</ErrorP>} */}
@@ -153,17 +193,17 @@ const ReNewPage = ({ pageData }: SSRProps<PageProps>) => {
 
 
             <Label>Url to client modpack</Label><br />
-            <SInput type="text" {...register("url")} placeholder="url to mod list" /><br />
+            <SInput type="text" {...register("url")} placeholder="url to mod list, /client_pack?name=<name>&token=<clientPackToken> serves one built from the server" /><br />
             {errors.url && <ErrorP>{errors.url.message}</ErrorP>}
 
             <Label>Maximum memory, in GB <DisplayRange range={memoryLimits}/> </Label><br />
             <NumberInput name="maxMemory" type="float" control={control} placeholder="max allowed memory consumption" /><br />
 
             <Label>Port, {ports?.portsTaken.portLimits ? <DisplayRange range={ports.portsTaken.portLimits}/> : null}</Label><br />
-            <NumberInput name="port" type="int" control={control} placeholder="server port" /><br />
+            <NumberInput name="port" type="int" control={control} placeholder="server port, free one is picked when empty" /><br />
 
             <Label>Rcon, {ports?.portsTaken.rconLimits ? <DisplayRange range={ports.portsTaken.rconLimits}/> : null}</Label><br />
-            <NumberInput name="rcon" type="int" control={control} placeholder="server rcon" /><br />
+            <NumberInput name="rcon" type="int" control={control} placeholder="server rcon, free one is picked when empty" /><br />
 
         </form>    
     </>
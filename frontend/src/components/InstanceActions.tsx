@@ -9,6 +9,9 @@ type Props = {
     state: string
 }
 
+// prefix of the error the server gives when preflight check blocks the start
+const PREFLIGHT_FAILED = "preflight check failed";
+
 const InstanceActions = ({name, state, deselect}: Props) => {
 
     const [remove] = useMutation(gql`
@@ -18,8 +21,8 @@ const InstanceActions = ({name, state, deselect}: Props) => {
     `);
 
     const [ctl] = useMutation(gql`
-        mutation Mutation($name: String!,$shouldRun: Boolean!) {
-            shouldRun(name: $name,shouldRun: $shouldRun)
+        mutation Mutation($name: String!,$shouldRun: Boolean!,$force: Boolean) {
+            shouldRun(name: $name,shouldRun: $shouldRun,force: $force)
         }
     `);
 
@@ -46,6 +49,22 @@ const InstanceActions = ({name, state, deselect}: Props) => {
                 name,
                 shouldRun
             }
+        }).catch((e) => {
+            // only preflight check may be overridden, other errors are just shown
+            if (!shouldRun || !e.message.includes(PREFLIGHT_FAILED)) {
+                alert(e.message)
+                return
+            }
+
+            if (confirm(`${e.message}\n\nStart anyway?`)) {
+                ctl({
+                    variables: {
+                        name,
+                        shouldRun,
+                        force: true
+                    }
+                })
+            }
         })
     };
 
@@ -57,15 +76,23 @@ const InstanceActions = ({name, state, deselect}: Props) => {
         window.location.href = `/rcon?name=${name}`;
     };
 
+    const consoleOnClick = () => {
+        window.location.href = `/console?name=${name}`;
+    };
+
+    // server process is up even when rcon is being reconnected
+    const running = state == "Running" || state == "RconUnavailable";
+
     return <>
-        {(state == "Running")
+        {running
             ? <>
                 <Btn onClick={switchServer(false)}>Stop</Btn><br />
-                <Btn onClick={rconOnClick}>Rcon</Btn><br />
+                {(state == "Running") ? <><Btn onClick={rconOnClick}>Rcon</Btn><br /></> : null}
+                <Btn onClick={consoleOnClick}>Console</Btn><br />
             </>
             : null
         }
-        {(state !== "Running")
+        {!running
             ? <>
                 <Btn onClick={switchServer(true)}>Start</Btn><br />
                 <Btn onClick={alterOnClick}>Alter</Btn><br />
@@ -1,5 +1,7 @@
 import styled from "styled-components";
-import {InstanceDescriptor} from "../model";
+import {MouseEvent} from "react";
+import {gql, useLazyQuery} from "@apollo/client";
+import {DiskUsage, InstanceDescriptor} from "../model";
 
 const Inner = styled.div<{selected: boolean}>`
     background-color: ${(p) => p.selected ? '#ff9034' : 'rgba(255, 255, 255, 0.9)'};
@@ -34,6 +36,7 @@ const InfoItem = styled.div`
 type Props = {
     instance: InstanceDescriptor,
     state: string, 
+    disk: DiskUsage | null,
     selected: boolean,
     setSelected: () => void
 }
@@ -49,9 +52,37 @@ const RightTriangle = () => (<RTInner viewBox="0 0 100 100" >
 </RTInner>)
 
 
-const Desc = ({instance, state, selected, setSelected}: Props) => {
+const GB = 1024 * 1024 * 1024;
+
+const Desc = ({instance, state, disk, selected, setSelected}: Props) => {
     const {name, memory, max_memory, port} = instance;
 
+    const [getToken] = useLazyQuery<{clientPackToken: string}>(gql`
+        query ClientPackToken($name: String!, $password: String!) {
+            clientPackToken(name: $name, password: $password)
+        }
+    `, { fetchPolicy: "no-cache" });
+
+    // the admin password only fetches the token, it never goes into the url
+    const downloadClientPack = (format: string) => async (e: MouseEvent) => {
+        e.preventDefault();
+
+        let password = prompt("Please enter the password to download the client pack");
+
+        if (!password) {
+            return;
+        }
+
+        const result = await getToken({ variables: { name, password } });
+
+        if (result.error || !result.data) {
+            alert(`Cannot get client pack token: ${result.error?.message}`);
+            return;
+        }
+
+        window.location.href = `/client_pack?name=${encodeURIComponent(name)}&format=${format}&token=${encodeURIComponent(result.data.clientPackToken)}`;
+    };
+
     return (
         <Inner selected={selected}>
             <Title onClick={setSelected}><RightTriangle />{name}</Title>
@@ -59,8 +90,15 @@ const Desc = ({instance, state, selected, setSelected}: Props) => {
                 <InfoItem>State: {state}</InfoItem>
                 <InfoItem>Memory usage: {memory ? `${memory} GB` : 'N/A'} </InfoItem>
                 <InfoItem>Max memory: {max_memory} GB</InfoItem>
-                <InfoItem>Port: {port}</InfoItem>
+                <InfoItem>Port: {instance.ports?.port ?? port}</InfoItem>
+                {instance.ports && <InfoItem>Rcon: {instance.ports.rcon}</InfoItem>}
+                {Object.entries(instance.ports?.extra ?? {}).map(([n, p]) => <InfoItem key={n}>{n}: {p}</InfoItem>)}
+                <InfoItem>Disk: {disk
+                    ? `${(disk.total / GB).toFixed(2)} GB${disk.quota ? ` of ${(disk.quota / GB).toFixed(2)} GB` : ''}${disk.over_quota ? ', over quota!' : disk.near_quota ? ', near quota' : ''}`
+                    : 'N/A'}
+                </InfoItem>
                 <InfoItem>Mods URL: <a href={instance.mods}>{instance.mods}</a></InfoItem>
+                <InfoItem>Client pack: <a href="#" onClick={downloadClientPack("mrpack")}>mrpack</a> <a href="#" onClick={downloadClientPack("zip")}>zip</a></InfoItem>
             </Info>
         </Inner>
     );
@@ -1,4 +1,4 @@
-export type ServerState = "Running" | "Stopped" | "Crashed"
+export type ServerState = "Running" | "RconUnavailable" | "Stopped" | "Crashed"
 
 export type InstanceDescriptor = {
     name: string,
@@ -9,7 +9,26 @@ export type InstanceDescriptor = {
     
     java_args: string[],
     max_memory: number,
-    port: number
+    port: number,
+    ports?: {
+        port: number,
+        rcon: number,
+        extra?: Record<string, number>
+    },
+    disk_quota: number | null,
+    hostnames?: string[]
+}
+
+export type DiskUsage = {
+    total: number,
+    worlds: number,
+    mods: number,
+    logs: number,
+    backups: number,
+    other: number,
+    quota: number | null,
+    near_quota: boolean,
+    over_quota: boolean
 }
 
 export type PortTaken = {
@@ -42,6 +42,10 @@ module.exports = {
       dependOn: 'shared',
       import: './src/Rcon.tsx',
     },
+    console: {
+      dependOn: 'shared',
+      import: './src/Console.tsx',
+    },
     renew: {
       dependOn: ['shared', 'validate'],
       import: './src/Renew.tsx',